@@ -0,0 +1,257 @@
+// # Warning
+//
+// Everything in this module assumes that the AVX2 feature is available.
+
+use std::arch::x86_64::{
+    __m256i, _mm256_cmpeq_epi8, _mm256_load_si256, _mm256_movemask_epi8, _mm256_or_si256,
+    _mm256_set1_epi8, _mm256_setzero_si256,
+};
+
+use table::ByteList;
+use twoway::TwoWay;
+
+const BYTES_PER_OPERATION: usize = 32;
+
+/// Clears the bits of a 32-byte aligned chunk's mask that belong to
+/// bytes outside of the haystack `start..end`.
+#[inline]
+fn within(mask: u32, chunk: usize, start: usize, end: usize) -> u64 {
+    let mut mask = u64::from(mask);
+
+    // Remove any matches after the end of the haystack
+    let trailing = end - chunk;
+    if trailing < BYTES_PER_OPERATION {
+        mask &= (1 << trailing) - 1;
+    }
+
+    // Remove any matches before the start of the haystack
+    if chunk < start {
+        mask &= !((1 << (start - chunk)) - 1);
+    }
+
+    mask
+}
+
+/// Compares each chunk against every byte in the set, one broadcast
+/// byte at a time, and combines the results.
+pub struct Bytes<'s> {
+    set: &'s ByteList,
+}
+
+impl<'s> Bytes<'s> {
+    pub const fn new(set: &'s ByteList) -> Self {
+        Bytes { set }
+    }
+
+    /// Compares an entire 32-byte chunk, returning one bit per byte.
+    ///
+    /// The chunk must be 32-byte aligned so that the read cannot
+    /// cross a page boundary.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn mask(&self, chunk: *const u8) -> u32 {
+        debug_assert_eq!(0, chunk as usize % BYTES_PER_OPERATION);
+
+        let haystack = _mm256_load_si256(chunk as *const __m256i);
+
+        let mut matched = _mm256_setzero_si256();
+        for &b in self.set.as_slice() {
+            matched = _mm256_or_si256(matched, _mm256_cmpeq_epi8(haystack, _mm256_set1_epi8(b as i8)));
+        }
+        _mm256_movemask_epi8(matched) as u32
+    }
+
+    /// Reads only 32-byte aligned chunks, for the same reason that
+    /// the `PCMPxSTRx` searches read only 16-byte aligned chunks.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let last_chunk = (end - 1) & !0x1F;
+        let mut chunk = start & !0x1F;
+
+        loop {
+            let found = within(self.mask(chunk as *const u8), chunk, start, end);
+            if found != 0 {
+                return Some(chunk + found.trailing_zeros() as usize - start);
+            }
+
+            if chunk == last_chunk {
+                return None;
+            }
+            chunk += BYTES_PER_OPERATION;
+        }
+    }
+}
+
+/// Uses the rarest byte of the needle to find candidates, then checks
+/// each candidate with the Two-Way algorithm.
+pub struct ByteSubstring<'a> {
+    two_way: TwoWay<'a>,
+    anchor: ByteList,
+}
+
+impl<'a> ByteSubstring<'a> {
+    pub const fn new(needle: &'a [u8]) -> Self {
+        let two_way = TwoWay::new(needle);
+
+        let mut bytes = [0; 16];
+        let len = if let Some(anchor) = two_way.anchor_byte() {
+            bytes[0] = anchor;
+            1
+        } else {
+            0
+        };
+
+        ByteSubstring {
+            two_way,
+            anchor: ByteList::new(bytes, len),
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.find(haystack, |candidates| Bytes::new(&self.anchor).find(candidates))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use simd::test::{long_needle_and_haystack, with_guarded_string};
+
+    /// Running these tests on a machine without AVX2 would crash the
+    /// whole test suite, so they quietly pass instead.
+    fn has_avx2() -> bool {
+        is_x86_feature_detected!("avx2")
+    }
+
+    /// Creates a set of bytes and an offset inside them.
+    fn haystack() -> BoxedStrategy<(Vec<u8>, usize)> {
+        any::<Vec<u8>>()
+            .prop_flat_map(|data| {
+                let len = 0..=data.len();
+                (Just(data), len)
+            })
+            .boxed()
+    }
+
+    /// Creates an array and the number of valid values
+    fn needle() -> BoxedStrategy<([u8; 16], usize)> {
+        (any::<[u8; 16]>(), 0..=16_usize).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does(
+            ((needle, len), (haystack, start)) in (needle(), haystack())
+        ) {
+            if !has_avx2() {
+                return Ok(());
+            }
+
+            let haystack = &haystack[start..];
+            let set = ByteList::new(needle, len);
+            let bytes = Bytes::new(&set);
+            let needle = &needle[..len];
+
+            let us = unsafe { bytes.find(haystack) };
+            let them = haystack.iter().position(|b| needle.contains(b));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_as_find_does_for_byte_substrings(
+            (needle, haystack) in (any::<Vec<u8>>(), any::<Vec<u8>>())
+        ) {
+            if !has_avx2() {
+                return Ok(());
+            }
+
+            let substring = ByteSubstring::new(&needle);
+
+            let us = unsafe { substring.find(&haystack) };
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_as_find_does_for_long_byte_substrings(
+            (needle, haystack) in long_needle_and_haystack()
+        ) {
+            if !has_avx2() {
+                return Ok(());
+            }
+
+            let substring = ByteSubstring::new(&needle);
+
+            let us = unsafe { substring.find(&haystack) };
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+    }
+
+    #[test]
+    fn byte_substring_spans_chunks() {
+        if !has_avx2() {
+            return;
+        }
+
+        let mut haystack = vec![b'z'; 64];
+        haystack[40] = b'a';
+        haystack[41] = b'b';
+
+        let substring = ByteSubstring::new(b"ab");
+        unsafe {
+            assert_eq!(Some(40), substring.find(&haystack));
+            assert_eq!(None, substring.find(&haystack[..41]));
+        }
+    }
+
+    #[test]
+    fn works_at_page_boundary() {
+        if !has_avx2() {
+            return;
+        }
+
+        // A 32-byte string, so that every suffix starts at a
+        // different offset from the aligned chunk.
+        with_guarded_string("0123456789abcdef0123456789ABCDEF", |text| {
+            let set = ByteList::new(*b"F\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0", 1);
+            let needle = Bytes::new(&set);
+
+            for offset in 0..text.len() {
+                let tail = &text[offset..];
+                unsafe {
+                    assert_eq!(Some(tail.len() - 1), needle.find(tail.as_bytes()));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn byte_substring_works_at_page_boundary() {
+        if !has_avx2() {
+            return;
+        }
+
+        with_guarded_string("0123456789abcdef0123456789ABCDEF", |text| {
+            let needle = ByteSubstring::new(b"EF");
+
+            for offset in 0..text.len() - 1 {
+                let tail = &text[offset..];
+                unsafe {
+                    assert_eq!(Some(tail.len() - 2), needle.find(tail.as_bytes()));
+                }
+            }
+        });
+    }
+}
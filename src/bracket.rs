@@ -0,0 +1,365 @@
+//! Parses regex-style bracket expressions, such as `[a-z0-9_-]`,
+//! `[^"\\]` or `[[:space:]]`, into a set of bytes.
+//!
+//! The syntax follows POSIX, with a few additions from regex
+//! engines:
+//!
+//! - A `]` directly after the opening `[` or `[^` is a literal, as
+//!   is a `-` at the start or end of the class.
+//! - A backslash escapes the next punctuation character, and `\n`,
+//!   `\r`, `\t`, `\f`, `\v`, `\0` and `\xHH` name a byte.
+//! - Named classes such as `[:alpha:]` are the POSIX classes in the
+//!   C locale.
+
+use error::{ClassErrorKind, Error};
+use table::ByteTable;
+
+pub fn parse(expr: &str) -> Result<ByteTable, Error> {
+    Parser {
+        expr: expr.as_bytes(),
+        pos: 0,
+    }
+    .parse()
+}
+
+struct Parser<'a> {
+    expr: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self) -> Result<ByteTable, Error> {
+        if let Some(index) = self.expr.iter().position(|b| !b.is_ascii()) {
+            return Err(Error::NonAscii {
+                byte: self.expr[index],
+                index,
+            });
+        }
+
+        if !self.eat(b'[') {
+            return Err(self.error(ClassErrorKind::MissingOpenBracket));
+        }
+        let negated = self.eat(b'^');
+
+        let mut table = ByteTable::new(&[]);
+        let mut first = true;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ClassErrorKind::UnclosedBracket)),
+                Some(b']') if !first => {
+                    self.pos += 1;
+                    break;
+                }
+                _ if self.rest().starts_with(b"[:") => self.named_class(&mut table)?,
+                _ => self.byte_or_range(&mut table)?,
+            }
+            first = false;
+        }
+
+        if self.pos != self.expr.len() {
+            return Err(self.error(ClassErrorKind::TrailingCharacters));
+        }
+
+        Ok(if negated { table.complement() } else { table })
+    }
+
+    fn byte_or_range(&mut self, table: &mut ByteTable) -> Result<(), Error> {
+        let start = self.pos;
+        let lo = self.byte()?;
+
+        // A `-` right before the closing `]` is a literal.
+        if self.peek() != Some(b'-') || self.rest().starts_with(b"-]") {
+            table.insert(lo);
+            return Ok(());
+        }
+        self.pos += 1;
+
+        if self.rest().starts_with(b"[:") {
+            return Err(self.error(ClassErrorKind::InvalidRange));
+        }
+        let hi = self.byte()?;
+        if lo > hi {
+            return Err(Error::InvalidClass {
+                kind: ClassErrorKind::InvalidRange,
+                index: start,
+            });
+        }
+
+        table.insert_range(lo, hi);
+        Ok(())
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let b = match self.next() {
+            Some(b) => b,
+            None => return Err(self.error(ClassErrorKind::UnclosedBracket)),
+        };
+        if b != b'\\' {
+            return Ok(b);
+        }
+
+        let start = self.pos - 1;
+        let invalid = Error::InvalidClass {
+            kind: ClassErrorKind::InvalidEscape,
+            index: start,
+        };
+        match self.next() {
+            None => Err(self.error(ClassErrorKind::UnclosedBracket)),
+            Some(b'n') => Ok(b'\n'),
+            Some(b'r') => Ok(b'\r'),
+            Some(b't') => Ok(b'\t'),
+            Some(b'f') => Ok(0x0C),
+            Some(b'v') => Ok(0x0B),
+            Some(b'0') => Ok(0),
+            Some(b'x') => match self.rest() {
+                &[hi, lo, ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                    self.pos += 2;
+                    Ok(hex_value(hi) << 4 | hex_value(lo))
+                }
+                _ => Err(invalid),
+            },
+            Some(b) if b.is_ascii_punctuation() => Ok(b),
+            Some(_) => Err(invalid),
+        }
+    }
+
+    fn named_class(&mut self, table: &mut ByteTable) -> Result<(), Error> {
+        let start = self.pos;
+        let name_start = start + 2;
+        let name_len = match self.expr[name_start..].windows(2).position(|w| w == b":]") {
+            Some(len) => len,
+            None => return Err(self.error(ClassErrorKind::UnclosedBracket)),
+        };
+        let name = &self.expr[name_start..name_start + name_len];
+
+        let predicate: fn(&u8) -> bool = match name {
+            b"alnum" => u8::is_ascii_alphanumeric,
+            b"alpha" => u8::is_ascii_alphabetic,
+            b"blank" => |&b| b == b' ' || b == b'\t',
+            b"cntrl" => u8::is_ascii_control,
+            b"digit" => u8::is_ascii_digit,
+            b"graph" => u8::is_ascii_graphic,
+            b"lower" => u8::is_ascii_lowercase,
+            b"print" => |&b| b == b' ' || b.is_ascii_graphic(),
+            b"punct" => u8::is_ascii_punctuation,
+            // Unlike `is_ascii_whitespace`, this includes vertical tab.
+            b"space" => |&b| b == 0x0B || b.is_ascii_whitespace(),
+            b"upper" => u8::is_ascii_uppercase,
+            b"xdigit" => u8::is_ascii_hexdigit,
+            _ => {
+                return Err(Error::InvalidClass {
+                    kind: ClassErrorKind::UnknownClassName,
+                    index: start,
+                })
+            }
+        };
+
+        for b in (0..128).filter(predicate) {
+            table.insert(b);
+        }
+        self.pos = name_start + name_len + 2;
+
+        // A named class can't be the start of a range either.
+        if self.peek() == Some(b'-') && !self.rest().starts_with(b"-]") {
+            return Err(self.error(ClassErrorKind::InvalidRange));
+        }
+        Ok(())
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.expr[self.pos..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.rest().first().cloned()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek();
+        if b.is_some() {
+            self.pos += 1;
+        }
+        b
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self, kind: ClassErrorKind) -> Error {
+        Error::InvalidClass {
+            kind,
+            index: self.pos,
+        }
+    }
+}
+
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::{collection::vec as vec_strat, prelude::*};
+
+    use super::*;
+    use BracketClass;
+
+    fn members(expr: &str) -> Vec<u8> {
+        parse(expr).unwrap().bytes()
+    }
+
+    fn error(expr: &str) -> (ClassErrorKind, usize) {
+        match parse(expr) {
+            Err(Error::InvalidClass { kind, index }) => (kind, index),
+            other => panic!("expected an invalid class, got {:?}", other.map(|t| t.bytes())),
+        }
+    }
+
+    #[test]
+    fn bytes_and_ranges_are_parsed() {
+        assert_eq!(members("[abc]"), b"abc");
+        assert_eq!(members("[a-e0-2_]"), b"012_abcde");
+        assert_eq!(members("[-a]"), b"-a");
+        assert_eq!(members("[a-]"), b"-a");
+        assert_eq!(members("[]a]"), b"]a");
+        assert_eq!(members("[!--]"), b"!\"#$%&'()*+,-");
+    }
+
+    #[test]
+    fn negated_classes_are_complemented() {
+        let not_quote = members(r#"[^"\\]"#);
+        assert_eq!(not_quote.len(), 254);
+        assert!(!not_quote.contains(&b'"'));
+        assert!(!not_quote.contains(&b'\\'));
+        assert!(not_quote.contains(&0xFF));
+
+        assert_eq!(members("[^]]").len(), 255);
+    }
+
+    #[test]
+    fn escapes_name_a_byte() {
+        assert_eq!(members(r"[\n\r\t\f\v\0]"), b"\0\t\n\x0B\x0C\r");
+        assert_eq!(members(r"[\x80-\xFF]"), (0x80..=0xFF).collect::<Vec<u8>>());
+        assert_eq!(members(r"[\]\-\^]"), b"-]^");
+    }
+
+    #[test]
+    fn named_classes_match_posix() {
+        assert_eq!(members("[[:digit:]]"), b"0123456789");
+        assert_eq!(members("[[:space:]]"), b"\t\n\x0B\x0C\r ");
+        assert_eq!(members("[[:blank:]]"), b"\t ");
+        assert_eq!(members("[[:xdigit:]_]"), b"0123456789ABCDEF_abcdef");
+        assert_eq!(members("[[:print:]]").len(), 95);
+        assert_eq!(members("[^[:cntrl:][:print:]]"), (0x80..=0xFF).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn malformed_expressions_are_reported() {
+        assert_eq!(error("abc"), (ClassErrorKind::MissingOpenBracket, 0));
+        assert_eq!(error("[abc"), (ClassErrorKind::UnclosedBracket, 4));
+        assert_eq!(error("[]"), (ClassErrorKind::UnclosedBracket, 2));
+        assert_eq!(error(r"[a\"), (ClassErrorKind::UnclosedBracket, 3));
+        assert_eq!(error("[abc]d"), (ClassErrorKind::TrailingCharacters, 5));
+        assert_eq!(error("[a z-a]"), (ClassErrorKind::InvalidRange, 3));
+        assert_eq!(error("[a-[:digit:]]"), (ClassErrorKind::InvalidRange, 3));
+        assert_eq!(error("[[:digit:]-z]"), (ClassErrorKind::InvalidRange, 10));
+        assert_eq!(error("[[:word:]]"), (ClassErrorKind::UnknownClassName, 1));
+        assert_eq!(error("[[:digit]"), (ClassErrorKind::UnclosedBracket, 1));
+        assert_eq!(error(r"[\q]"), (ClassErrorKind::InvalidEscape, 1));
+        assert_eq!(error(r"[\xZZ]"), (ClassErrorKind::InvalidEscape, 1));
+        assert_eq!(error(r"[\x1]"), (ClassErrorKind::InvalidEscape, 1));
+        assert_eq!(error(r"[\x+1]"), (ClassErrorKind::InvalidEscape, 1));
+    }
+
+    #[test]
+    fn non_ascii_characters_are_rejected() {
+        assert_eq!(
+            parse("[aé]").err(),
+            Some(Error::NonAscii {
+                byte: 0xC3,
+                index: 2
+            })
+        );
+    }
+
+    #[test]
+    fn each_strategy_matches_the_parsed_table() {
+        use BracketStrategy;
+
+        fn strategy(class: &BracketClass) -> &'static str {
+            match class.strategy {
+                BracketStrategy::Bytes(_) => "Bytes",
+                BracketStrategy::NotBytes(_) => "NotBytes",
+                BracketStrategy::Ranges(_) => "Ranges",
+                BracketStrategy::Set(_) => "Set",
+            }
+        }
+
+        let cases = [
+            ("[abc]", "Bytes"),
+            ("[^ab]", "NotBytes"),
+            (r"[\x00-\xFF]", "NotBytes"),
+            ("[a-z0-9]", "Ranges"),
+            ("[[:alnum:]_-]", "Ranges"),
+            ("[a-z02468ACEG]", "Set"),
+            (r"[^\x00-\xFF]", "Bytes"),
+        ];
+
+        // Every byte, in both orders, so each one is found first and
+        // last in turn.
+        let mut haystack: Vec<u8> = (0..=255).collect();
+        haystack.extend((0..=255).rev());
+
+        for &(expr, expected) in &cases {
+            let table = parse(expr).unwrap();
+            let class: BracketClass = expr.parse().unwrap();
+            assert_eq!(strategy(&class), expected, "{}", expr);
+
+            let matches: Vec<_> = (0..haystack.len()).filter(|&i| table.contains(haystack[i])).collect();
+            assert_eq!(class.find_iter(&haystack).collect::<Vec<_>>(), matches, "{}", expr);
+            assert_eq!(class.find(&haystack), matches.first().cloned(), "{}", expr);
+            assert_eq!(class.rfind(&haystack), matches.last().cloned(), "{}", expr);
+
+            for b in 0..=255 {
+                assert_eq!(class.find(&[b]).is_some(), table.contains(b), "{} {:#04X}", expr, b);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn every_strategy_finds_the_same_bytes(
+            needle in vec_strat(any::<u8>(), 1..=255),
+            negated in any::<bool>(),
+            haystack in any::<Vec<u8>>(),
+        ) {
+            let mut expr = String::from(if negated { "[^" } else { "[" });
+            for b in &needle {
+                expr.push_str(&format!("\\x{:02X}", b));
+            }
+            expr.push(']');
+
+            let class: BracketClass = expr.parse().unwrap();
+            let matches = |b: &u8| needle.contains(b) != negated;
+
+            assert_eq!(class.find(&haystack), haystack.iter().position(matches));
+            assert_eq!(class.rfind(&haystack), haystack.iter().rposition(matches));
+
+            let expected: Vec<_> = (0..haystack.len()).filter(|&i| matches(&haystack[i])).collect();
+            assert_eq!(class.find_iter(&haystack).collect::<Vec<_>>(), expected);
+
+            let mut reversed: Vec<_> = class.find_iter(&haystack).rev().collect();
+            reversed.reverse();
+            assert_eq!(reversed, expected);
+        }
+    }
+}
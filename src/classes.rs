@@ -0,0 +1,120 @@
+//! Commonly searched-for sets of ASCII characters.
+//!
+//! ```
+//! use jetscii::classes;
+//!
+//! let line = "key = value";
+//! assert_eq!(classes::WHITESPACE.find(line), Some(3));
+//! assert_eq!(classes::DIGITS.find(line), None);
+//! ```
+
+use {AsciiCharsConst, AsciiRangesConst};
+
+/// ASCII whitespace, as defined by [`u8::is_ascii_whitespace`]:
+/// space, tab, line feed, form feed and carriage return.
+pub static WHITESPACE: AsciiCharsConst = ascii_chars!(" \t\n\x0C\r");
+
+/// `0` to `9`, as defined by [`u8::is_ascii_digit`].
+pub static DIGITS: AsciiRangesConst = ascii_ranges!('0'..='9');
+
+/// `0` to `9`, `a` to `f` and `A` to `F`, as defined by
+/// [`u8::is_ascii_hexdigit`].
+pub static HEX_DIGITS: AsciiRangesConst = ascii_ranges!('0'..='9', 'a'..='f', 'A'..='F');
+
+/// `a` to `z`, as defined by [`u8::is_ascii_lowercase`].
+pub static LOWERCASE: AsciiRangesConst = ascii_ranges!('a'..='z');
+
+/// `A` to `Z`, as defined by [`u8::is_ascii_uppercase`].
+pub static UPPERCASE: AsciiRangesConst = ascii_ranges!('A'..='Z');
+
+/// `a` to `z` and `A` to `Z`, as defined by
+/// [`u8::is_ascii_alphabetic`].
+pub static ALPHABETIC: AsciiRangesConst = ascii_ranges!('a'..='z', 'A'..='Z');
+
+/// `0` to `9`, `a` to `z` and `A` to `Z`, as defined by
+/// [`u8::is_ascii_alphanumeric`].
+pub static ALPHANUMERIC: AsciiRangesConst = ascii_ranges!('0'..='9', 'a'..='z', 'A'..='Z');
+
+/// The printable characters other than letters, digits and space, as
+/// defined by [`u8::is_ascii_punctuation`].
+pub static PUNCTUATION: AsciiRangesConst =
+    ascii_ranges!('!'..='/', ':'..='@', '['..='`', '{'..='~');
+
+/// The printable characters other than space, as defined by
+/// [`u8::is_ascii_graphic`].
+pub static GRAPHIC: AsciiRangesConst = ascii_ranges!('!'..='~');
+
+/// `NUL` to `US` and `DEL`, as defined by [`u8::is_ascii_control`].
+pub static CONTROL: AsciiRangesConst = ascii_ranges!('\0'..='\x1F', '\x7F'..='\x7F');
+
+/// The characters that must be escaped in XML or HTML text and
+/// attribute values: `<`, `>`, `&`, `"` and `'`.
+pub static XML_SPECIAL: AsciiCharsConst = ascii_chars!("<>&\"'");
+
+/// The characters that must be escaped in a JSON string: `"`, `\`
+/// and the control characters `NUL` to `US`.
+pub static JSON_STRING_ESCAPES: AsciiRangesConst =
+    ascii_ranges!('\0'..='\x1F', '"'..='"', '\\'..='\\');
+
+/// The characters that separate words in a POSIX shell when
+/// unquoted: `|`, `&`, `;`, `(`, `)`, `<`, `>`, space, tab and line
+/// feed.
+pub static SHELL_METACHARACTERS: AsciiCharsConst = ascii_chars!("|&;()<> \t\n");
+
+/// The path separators of every supported platform: `/` and `\`.
+pub static PATH_SEPARATORS: AsciiCharsConst = ascii_chars!("/\\");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns every ASCII character that `find` matches.
+    fn members<F>(find: F) -> Vec<u8>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        (0..128u8)
+            .filter(|&b| find(std::str::from_utf8(&[b]).unwrap()).is_some())
+            .collect()
+    }
+
+    fn expected<F>(predicate: F) -> Vec<u8>
+    where
+        F: Fn(&u8) -> bool,
+    {
+        (0..128u8).filter(predicate).collect()
+    }
+
+    #[test]
+    fn classes_match_the_standard_library() {
+        assert_eq!(members(|s| WHITESPACE.find(s)), expected(u8::is_ascii_whitespace));
+        assert_eq!(members(|s| DIGITS.find(s)), expected(u8::is_ascii_digit));
+        assert_eq!(members(|s| HEX_DIGITS.find(s)), expected(u8::is_ascii_hexdigit));
+        assert_eq!(members(|s| LOWERCASE.find(s)), expected(u8::is_ascii_lowercase));
+        assert_eq!(members(|s| UPPERCASE.find(s)), expected(u8::is_ascii_uppercase));
+        assert_eq!(members(|s| ALPHABETIC.find(s)), expected(u8::is_ascii_alphabetic));
+        assert_eq!(members(|s| ALPHANUMERIC.find(s)), expected(u8::is_ascii_alphanumeric));
+        assert_eq!(members(|s| PUNCTUATION.find(s)), expected(u8::is_ascii_punctuation));
+        assert_eq!(members(|s| GRAPHIC.find(s)), expected(u8::is_ascii_graphic));
+        assert_eq!(members(|s| CONTROL.find(s)), expected(u8::is_ascii_control));
+    }
+
+    #[test]
+    fn special_characters_are_matched() {
+        assert_eq!(members(|s| XML_SPECIAL.find(s)), b"\"&'<>");
+        assert_eq!(members(|s| SHELL_METACHARACTERS.find(s)), b"\t\n &();<>|");
+        assert_eq!(members(|s| PATH_SEPARATORS.find(s)), b"/\\");
+
+        let mut json = expected(|&b| b < 0x20);
+        json.extend_from_slice(b"\"\\");
+        assert_eq!(members(|s| JSON_STRING_ESCAPES.find(s)), json);
+    }
+
+    #[test]
+    fn non_ascii_characters_are_never_matched() {
+        let haystack = "é→😀";
+        assert_eq!(CONTROL.find(haystack), None);
+        assert_eq!(GRAPHIC.find(haystack), None);
+        assert_eq!(WHITESPACE.find(haystack), None);
+    }
+}
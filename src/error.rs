@@ -0,0 +1,138 @@
+use std::{error, fmt};
+
+/// The reasons a searcher can't be constructed from the provided
+/// bytes, as returned by the `try_new` constructors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A set of ASCII characters contains a byte that is not ASCII.
+    NonAscii {
+        /// The offending byte.
+        byte: u8,
+        /// Its position in the array.
+        index: usize,
+    },
+    /// More than 16 bytes were provided.
+    TooManyBytes {
+        /// The number of bytes provided.
+        len: usize,
+    },
+    /// The number of valid bytes is negative.
+    NegativeLength {
+        /// The length provided.
+        len: i32,
+    },
+    /// A substring search was given an empty needle.
+    EmptyNeedle,
+    /// A bracket expression passed to [`BracketClass`][crate::BracketClass]
+    /// is malformed.
+    InvalidClass {
+        /// What is wrong with the expression.
+        kind: ClassErrorKind,
+        /// The position in the expression where the problem was
+        /// found.
+        index: usize,
+    },
+}
+
+/// The ways a bracket expression can be malformed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClassErrorKind {
+    /// The expression doesn't start with `[`.
+    MissingOpenBracket,
+    /// The expression ends before the closing `]`.
+    UnclosedBracket,
+    /// There is more input after the closing `]`.
+    TrailingCharacters,
+    /// A range such as `z-a` starts after it ends, or one of its ends
+    /// is a named class.
+    InvalidRange,
+    /// A named class such as `[:space:]` is not one of the POSIX
+    /// classes.
+    UnknownClassName,
+    /// A backslash is followed by something that can't be escaped.
+    InvalidEscape,
+}
+
+impl fmt::Display for ClassErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ClassErrorKind::MissingOpenBracket => "expected `[`",
+            ClassErrorKind::UnclosedBracket => "missing closing `]`",
+            ClassErrorKind::TrailingCharacters => "unexpected characters after `]`",
+            ClassErrorKind::InvalidRange => "invalid range",
+            ClassErrorKind::UnknownClassName => "unknown class name",
+            ClassErrorKind::InvalidEscape => "invalid escape",
+        })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NonAscii { byte, index } => {
+                write!(f, "non-ASCII byte 0x{:02X} at index {}", byte, index)
+            }
+            Error::TooManyBytes { len } => {
+                write!(f, "cannot have more than 16 bytes, found {}", len)
+            }
+            Error::NegativeLength { len } => write!(f, "length {} is negative", len),
+            Error::EmptyNeedle => f.write_str("cannot search for an empty needle"),
+            Error::InvalidClass { kind, index } => {
+                write!(f, "invalid character class at index {}: {}", index, kind)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Checks the number of valid bytes passed alongside a 16-byte
+/// array.
+pub(crate) fn check_len(len: i32) -> Result<(), Error> {
+    if len < 0 {
+        Err(Error::NegativeLength { len })
+    } else if len > 16 {
+        Err(Error::TooManyBytes { len: len as usize })
+    } else {
+        Ok(())
+    }
+}
+
+/// Reports the first byte that is not ASCII.
+pub(crate) fn check_ascii(bytes: &[u8]) -> Result<(), Error> {
+    match bytes.iter().position(|b| !b.is_ascii()) {
+        Some(index) => Err(Error::NonAscii {
+            byte: bytes[index],
+            index,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lengths_from_zero_to_sixteen_are_valid() {
+        for len in 0..=16 {
+            assert_eq!(check_len(len), Ok(()));
+        }
+        assert_eq!(check_len(-1), Err(Error::NegativeLength { len: -1 }));
+        assert_eq!(check_len(17), Err(Error::TooManyBytes { len: 17 }));
+    }
+
+    #[test]
+    fn the_first_non_ascii_byte_is_reported() {
+        assert_eq!(check_ascii(b"abc"), Ok(()));
+        assert_eq!(
+            check_ascii(b"a\xC3\xA9"),
+            Err(Error::NonAscii {
+                byte: 0xC3,
+                index: 1
+            })
+        );
+    }
+}
@@ -1,43 +1,401 @@
-// TODO: Try boxing the closure to see if we can hide the type
-// TODO: Or maybe use a function pointer?
+use table::ByteTable;
+
+#[cfg(any(test, all(not(target_arch = "x86_64"), not(feature = "portable-simd"))))]
+use twoway::TwoWay;
+
+/// Sets of up to this many bytes are compared a word at a time
+/// instead of calling the closure for every byte.
+const SWAR_MAX_LEN: usize = 3;
 
 pub struct Bytes<F>
 where
     F: Fn(u8) -> bool,
 {
-    fallback: F,
+    // Without a closure, bytes are looked up in the table instead.
+    fallback: Option<F>,
+    table: ByteTable,
+    swar: Option<Swar>,
 }
 
 impl<F> Bytes<F>
 where
     F: Fn(u8) -> bool,
 {
-    pub /* const */ fn new(fallback: F) -> Self {
-        Bytes { fallback }
+    /// Searches for bytes in inclusive ranges, given as pairs of the
+    /// low and high bytes.
+    pub const fn with_ranges(ranges: [u8; 16], len: i32, fallback: Option<F>) -> Self {
+        Bytes {
+            fallback,
+            table: ByteTable::from_ranges(ranges, len as usize),
+            swar: None,
+        }
+    }
+
+    /// Small sets will be searched without calling the closure for
+    /// every byte. Without a closure, the bytes are looked up in a
+    /// table instead.
+    pub const fn with_bytes(bytes: [u8; 16], len: i32, fallback: Option<F>) -> Self {
+        let len = len as usize;
+        Bytes {
+            fallback,
+            table: ByteTable::from_array(bytes, len),
+            swar: if len <= SWAR_MAX_LEN { Some(Swar::new(bytes, len)) } else { None },
+        }
+    }
+
+    /// The members of the set, even when a closure is used to
+    /// search for them.
+    pub fn table(&self) -> &ByteTable {
+        &self.table
+    }
+
+    #[inline]
+    fn matches(&self, b: u8) -> bool {
+        match self.fallback {
+            Some(ref fallback) => fallback(b),
+            None => self.table.contains(b),
+        }
     }
 
     pub fn find(&self, haystack: &[u8]) -> Option<usize> {
-        haystack.iter().cloned().position(&self.fallback)
+        match self.swar {
+            Some(ref swar) => swar.find(haystack),
+            None => haystack.iter().position(|&b| self.matches(b)),
+        }
+    }
+
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        match self.swar {
+            Some(ref swar) => swar.rfind(haystack),
+            None => haystack.iter().rposition(|&b| self.matches(b)),
+        }
+    }
+
+    pub fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        match self.swar {
+            Some(ref swar) => swar.find_not(haystack),
+            None => haystack.iter().position(|&b| !self.matches(b)),
+        }
+    }
+
+    pub fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        match self.swar {
+            Some(ref swar) => swar.rfind_not(haystack),
+            None => haystack.iter().rposition(|&b| !self.matches(b)),
+        }
+    }
+
+    // x86_64 always has SSE2 to iterate with instead.
+    #[cfg(not(target_arch = "x86_64"))]
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h [u8]) -> FindIter<'a, 'h, F> {
+        FindIter {
+            bytes: self,
+            haystack,
+            front: 0,
+            back: haystack.len(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub struct FindIter<'a, 'h, F>
+where
+    F: Fn(u8) -> bool,
+{
+    bytes: &'a Bytes<F>,
+    haystack: &'h [u8],
+    front: usize,
+    back: usize,
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl<'a, 'h, F> FindIter<'a, 'h, F>
+where
+    F: Fn(u8) -> bool,
+{
+    pub fn next(&mut self) -> Option<usize> {
+        let idx = self.bytes.find(&self.haystack[self.front..self.back])?;
+        let idx = self.front + idx;
+        self.front = idx + 1;
+        Some(idx)
+    }
+
+    pub fn next_back(&mut self) -> Option<usize> {
+        let idx = self.bytes.rfind(&self.haystack[self.front..self.back])?;
+        let idx = self.front + idx;
+        self.back = idx;
+        Some(idx)
     }
 }
 
+const WORD_LEN: usize = 8;
+const LOW_BITS: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// Sets the high bit of every byte of `word` that is zero, and clears
+/// every other bit.
+///
+/// The usual `(x - 0x01..) & !x & 0x80..` trick can report false
+/// matches after the first real one, so this variant never lets a
+/// carry cross from one byte into the next.
+#[inline]
+fn zero_bytes(word: u64) -> u64 {
+    !(((word & LOW_BITS) + LOW_BITS) | word | LOW_BITS)
+}
+
+/// Compares eight bytes at a time by treating them as a single `u64`
+/// ("SIMD within a register"). Every byte of the set costs a few
+/// arithmetic operations per word, so this only pays off for small
+/// sets.
+pub struct Swar {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+impl Swar {
+    const fn new(bytes: [u8; 16], len: usize) -> Self {
+        Swar { bytes, len }
+    }
+
+    /// Returns the high bit of each byte of `word` that is in the
+    /// set.
+    #[inline]
+    fn mask(&self, word: u64) -> u64 {
+        self.bytes[..self.len]
+            .iter()
+            .fold(0, |mask, &b| mask | zero_bytes(word ^ (u64::from(b) * 0x0101_0101_0101_0101)))
+    }
+
+    #[inline]
+    fn mask_chunk(&self, chunk: &[u8]) -> u64 {
+        let mut word = [0; WORD_LEN];
+        word.copy_from_slice(chunk);
+        self.mask(u64::from_le_bytes(word))
+    }
+
+    /// Unlike the SIMD implementations, this reads the haystack
+    /// through ordinary slices, so there is no risk of reading past
+    /// its end.
+    fn find_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u64) -> u64,
+    {
+        let chunks = haystack.chunks_exact(WORD_LEN);
+        let remainder = chunks.remainder();
+
+        for (i, chunk) in chunks.enumerate() {
+            let found = mask(self.mask_chunk(chunk)) & HIGH_BITS;
+            if found != 0 {
+                return Some(i * WORD_LEN + found.trailing_zeros() as usize / 8);
+            }
+        }
+
+        let tail = haystack.len() - remainder.len();
+        remainder
+            .iter()
+            .position(|&b| mask(self.mask(u64::from(b))) & 0x80 != 0)
+            .map(|idx| tail + idx)
+    }
+
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| mask)
+    }
+
+    pub fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| !mask)
+    }
+
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| mask)
+    }
+
+    pub fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| !mask)
+    }
+
+    fn rfind_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u64) -> u64,
+    {
+        let chunks = haystack.rchunks_exact(WORD_LEN);
+        let remainder = chunks.remainder();
+
+        for (i, chunk) in chunks.enumerate() {
+            let found = mask(self.mask_chunk(chunk)) & HIGH_BITS;
+            if found != 0 {
+                let last_match = (63 - found.leading_zeros() as usize) / 8;
+                return Some(haystack.len() - (i + 1) * WORD_LEN + last_match);
+            }
+        }
+
+        remainder
+            .iter()
+            .rposition(|&b| mask(self.mask(u64::from(b))) & 0x80 != 0)
+    }
+}
+
+/// Uses the rarest byte of the needle to find candidates, then checks
+/// each candidate with the Two-Way algorithm.
+// Every x86_64 machine can use SSE2 instead, and other platforms
+// can use `std::simd` when it's available. It is still built for
+// tests so that it runs against the same oracles everywhere.
+#[cfg(any(test, all(not(target_arch = "x86_64"), not(feature = "portable-simd"))))]
 pub struct ByteSubstring<'a> {
-    needle: &'a [u8],
+    two_way: TwoWay<'a>,
+    anchor: Swar,
 }
 
+#[cfg(any(test, all(not(target_arch = "x86_64"), not(feature = "portable-simd"))))]
 impl<'a> ByteSubstring<'a> {
-    pub /* const */ fn new(needle: &'a[u8]) -> Self {
-        ByteSubstring { needle }
+    pub const fn new(needle: &'a [u8]) -> Self {
+        let two_way = TwoWay::new(needle);
+
+        let mut bytes = [0; 16];
+        let len = if let Some(anchor) = two_way.anchor_byte() {
+            bytes[0] = anchor;
+            1
+        } else {
+            0
+        };
+
+        ByteSubstring {
+            two_way,
+            anchor: Swar::new(bytes, len),
+        }
     }
 
     #[cfg(feature = "pattern")]
     pub fn needle_len(&self) -> usize {
-        self.needle.len()
+        self.two_way.needle().len()
     }
 
     pub fn find(&self, haystack: &[u8]) -> Option<usize> {
-        haystack
-            .windows(self.needle.len())
-            .position(|window| window == self.needle)
+        self.two_way.find(haystack, |candidates| self.anchor.find(candidates))
+    }
+
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.rfind(haystack, |candidates| self.anchor.rfind(candidates))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Creates a set of bytes and an offset inside them.
+    fn haystack() -> BoxedStrategy<(Vec<u8>, usize)> {
+        any::<Vec<u8>>()
+            .prop_flat_map(|data| {
+                let len = 0..=data.len();
+                (Just(data), len)
+            })
+            .boxed()
+    }
+
+    /// Creates an array and the number of valid values
+    fn needle() -> BoxedStrategy<([u8; 16], usize)> {
+        (any::<[u8; 16]>(), 0..=SWAR_MAX_LEN).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does(
+            ((needle, len), (haystack, start)) in (needle(), haystack())
+        ) {
+            let haystack = &haystack[start..];
+            let swar = Swar::new(needle, len);
+            let needle = &needle[..len];
+
+            let us = swar.find(haystack);
+            let them = haystack.iter().position(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = swar.rfind(haystack);
+            let them = haystack.iter().rposition(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = swar.find_not(haystack);
+            let them = haystack.iter().position(|b| !needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = swar.rfind_not(haystack);
+            let them = haystack.iter().rposition(|b| !needle.contains(b));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_as_find_does_without_a_closure(
+            ((needle, len), (haystack, start)) in ((any::<[u8; 16]>(), 0..=16), haystack())
+        ) {
+            let haystack = &haystack[start..];
+            let bytes = Bytes::<fn(u8) -> bool>::with_bytes(needle, len, None);
+            let needle = &needle[..len as usize];
+
+            let us = bytes.find(haystack);
+            let them = haystack.iter().position(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = bytes.rfind(haystack);
+            let them = haystack.iter().rposition(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = bytes.find_not(haystack);
+            let them = haystack.iter().position(|b| !needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = bytes.rfind_not(haystack);
+            let them = haystack.iter().rposition(|b| !needle.contains(b));
+            assert_eq!(us, them);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn byte_substrings_work_as_find_does(
+            (needle, haystack) in (any::<Vec<u8>>(), any::<Vec<u8>>())
+        ) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = substring.find(&haystack);
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = substring.rfind(&haystack);
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn byte_substrings_find_repetitive_needles(
+            (needle, before, after) in (text(1..40), text(0..100), text(0..100))
+        ) {
+            // Two copies, so that `find` and `rfind` disagree.
+            let haystack = [&before[..], &needle, &after, &needle].concat();
+            let substring = ByteSubstring::new(&needle);
+
+            let us = substring.find(&haystack);
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = substring.rfind(&haystack);
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+    }
+
+    /// A small alphabet makes repetitive needles and partial matches
+    /// common.
+    fn text(len: ::std::ops::Range<usize>) -> BoxedStrategy<Vec<u8>> {
+        proptest::collection::vec(prop_oneof![Just(b'<'), Just(b'a'), Just(b'/'), Just(b'>')], len).boxed()
+    }
+
+    #[test]
+    fn zero_bytes_are_exact() {
+        // A borrow out of the zero byte must not mark the 0x01 above it.
+        assert_eq!(0x0000_0000_0000_0080, zero_bytes(0x0101_0101_0101_0100));
+        assert_eq!(HIGH_BITS, zero_bytes(0));
+        assert_eq!(0, zero_bytes(!0));
     }
 }
@@ -0,0 +1,56 @@
+/// A rough guess at how common each byte is in typical haystacks,
+/// from 0 (rare) to 255 (very common). The guesses favour text and
+/// markup, where spaces, lowercase letters and a handful of
+/// punctuation characters dominate, but also rank the padding bytes
+/// common in binary data highly.
+const fn rank(b: u8) -> u8 {
+    match b {
+        b' ' => 255,
+        b'\0' => 250,
+        b'e' => 245,
+        b't' | b'a' | b'o' | b'i' | b'n' => 240,
+        b's' | b'h' | b'r' => 235,
+        b'd' | b'l' | b'u' | b'c' | b'm' => 225,
+        b'a'..=b'z' => 200,
+        b'\n' | b'\r' | b'\t' => 195,
+        b',' | b'.' | b'"' | b'<' | b'>' | b'/' | b'=' | b'-' | b'_' | b':' => 190,
+        b'0'..=b'9' => 180,
+        0xFF => 170,
+        b'A'..=b'Z' => 160,
+        b'!'..=b'~' => 130,
+        _ => 50,
+    }
+}
+
+/// Picks the offset of the needle byte that is least likely to
+/// appear in the haystack. Searching for that byte (and the bytes
+/// after it) produces fewer false candidates than searching for the
+/// start of the needle.
+///
+/// Ties go to the earliest byte, which leaves the most needle after
+/// it for filters that compare more than one byte.
+pub const fn rarest_offset(needle: &[u8]) -> usize {
+    let mut rarest = 0;
+    let mut i = 1;
+    while i < needle.len() {
+        if rank(needle[i]) < rank(needle[rarest]) {
+            rarest = i;
+        }
+        i += 1;
+    }
+    rarest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn common_prefixes_are_skipped() {
+        assert_eq!(0, rarest_offset(b""));
+        assert_eq!(0, rarest_offset(b"Z"));
+        assert_eq!(4, rarest_offset(b"    Q    "));
+        assert_eq!(6, rarest_offset(b"\0\0\0\0\0\0\x01\0"));
+        assert_eq!(8, rarest_offset(b"the end {"));
+    }
+}
@@ -0,0 +1,195 @@
+use twoway::TwoWay;
+
+/// Needles at least this long are searched with shift tables when
+/// there is no SIMD filter to find their anchor byte.
+pub const LONG_NEEDLE_MIN_LEN: usize = 17;
+
+/// Searches for needles of at least [`LONG_NEEDLE_MIN_LEN`] bytes by
+/// skipping ahead with Horspool's bad-character rule, then checks
+/// each candidate with the Two-Way algorithm.
+///
+/// The skip looks at the haystack byte under the *last* byte of the
+/// needle (the *first* byte, when searching backwards) and moves the
+/// needle until that byte lines up with its nearest occurrence in the
+/// needle. On most haystacks this moves by close to the length of the
+/// needle, however repetitive the needle itself is.
+///
+/// SIMD anchor filters are faster than the skips on x86_64, even for
+/// long needles, so this is only used on other platforms.
+pub struct ByteSubstring<'a> {
+    two_way: TwoWay<'a>,
+    tables: SkipTables,
+}
+
+struct SkipTables {
+    // Shifts are capped at `u8::MAX` to keep the tables small; a
+    // shorter shift is always safe.
+    skip: [u8; 256],
+    skip_back: [u8; 256],
+}
+
+impl SkipTables {
+    const fn new(needle: &[u8]) -> Self {
+        let len = needle.len();
+
+        let mut skip = [cap(len); 256];
+        let mut i = 0;
+        while i + 1 < len {
+            skip[needle[i] as usize] = cap(len - 1 - i);
+            i += 1;
+        }
+
+        let mut skip_back = [cap(len); 256];
+        let mut i = len;
+        while i > 1 {
+            i -= 1;
+            skip_back[needle[i] as usize] = cap(i);
+        }
+
+        SkipTables { skip, skip_back }
+    }
+}
+
+impl<'a> ByteSubstring<'a> {
+    pub const fn new(needle: &'a [u8]) -> Self {
+        ByteSubstring {
+            // The skips line candidates up by their start, so that is
+            // where the prefilters are given the haystack.
+            two_way: TwoWay::with_anchor(needle, 0),
+            tables: SkipTables::new(needle),
+        }
+    }
+
+    #[cfg(feature = "pattern")]
+    pub fn needle_len(&self) -> usize {
+        self.two_way.needle().len()
+    }
+
+    /// Returns the offset of the first candidate in `haystack`, which
+    /// starts at the current candidate.
+    fn skip(&self, skip: &[u8; 256], haystack: &[u8]) -> Option<usize> {
+        let needle = self.two_way.anchor();
+        let last = needle.len() - 1;
+
+        let mut position = 0;
+        while let Some(&b) = haystack.get(position + last) {
+            if b == needle[last] {
+                return Some(position);
+            }
+            position += usize::from(skip[usize::from(b)]);
+        }
+
+        None
+    }
+
+    /// Returns the offset of the last candidate in `candidates`, which
+    /// holds every position that a candidate may start at.
+    fn skip_back(&self, skip_back: &[u8; 256], candidates: &[u8]) -> Option<usize> {
+        let first = self.two_way.anchor()[0];
+
+        let mut position = candidates.len().checked_sub(1)?;
+        loop {
+            let b = candidates[position];
+            if b == first {
+                return Some(position);
+            }
+            position = position.checked_sub(usize::from(skip_back[usize::from(b)]))?;
+        }
+    }
+
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        let skip = &self.tables.skip;
+        self.two_way.find(haystack, |candidates| self.skip(skip, candidates))
+    }
+
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        let skip_back = &self.tables.skip_back;
+        self.two_way.rfind(haystack, |candidates| self.skip_back(skip_back, candidates))
+    }
+}
+
+/// Caps a shift so that it fits in the skip tables.
+const fn cap(shift: usize) -> u8 {
+    if shift > u8::MAX as usize { u8::MAX } else { shift as u8 }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::{collection::vec, prelude::*};
+
+    use super::*;
+
+    /// A small alphabet makes repetitive needles and partial matches
+    /// common.
+    fn text(len: ::std::ops::Range<usize>) -> BoxedStrategy<Vec<u8>> {
+        vec(prop_oneof![Just(b'<'), Just(b'a'), Just(b'/'), Just(b'>')], len).boxed()
+    }
+
+    /// Creates a long needle and a haystack, which often contains the
+    /// needle.
+    fn needle_and_haystack() -> BoxedStrategy<(Vec<u8>, Vec<u8>)> {
+        (text(LONG_NEEDLE_MIN_LEN..300), text(0..200), text(0..200), any::<bool>())
+            .prop_map(|(needle, before, after, include)| {
+                let mut haystack = before;
+                if include {
+                    haystack.extend_from_slice(&needle);
+                }
+                haystack.extend(after);
+                (needle, haystack)
+            })
+            .boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does((needle, haystack) in needle_and_haystack()) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = substring.find(&haystack);
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = substring.rfind(&haystack);
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_as_find_does_for_arbitrary_bytes(
+            (needle, haystack) in (vec(any::<u8>(), LONG_NEEDLE_MIN_LEN..64), any::<Vec<u8>>())
+        ) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = substring.find(&haystack);
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = substring.rfind(&haystack);
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+    }
+
+    #[test]
+    fn repetitive_needle_is_found() {
+        let needle = b"<a></a><a></a><a></a><a></a><a></a>!";
+        let mut haystack = b"<a></a>".repeat(1000);
+        haystack.extend_from_slice(b"!<a></a>");
+
+        let substring = ByteSubstring::new(needle);
+        assert_eq!(Some(7000 - 35), substring.find(&haystack));
+        assert_eq!(Some(7000 - 35), substring.rfind(&haystack));
+        assert_eq!(None, substring.find(&haystack[..7000]));
+    }
+
+    #[test]
+    fn can_be_built_at_compile_time() {
+        const SUBSTRING: ByteSubstring<'static> = ByteSubstring::new(b"<a></a><a></a><a></a>!");
+
+        let haystack = b"<a></a><a></a><a></a><a></a>!<a></a>";
+        assert_eq!(Some(7), SUBSTRING.find(haystack));
+        assert_eq!(Some(7), SUBSTRING.rfind(haystack));
+    }
+}
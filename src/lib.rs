@@ -1,5 +1,6 @@
 #![cfg_attr(feature = "pattern", feature(pattern))]
 #![cfg_attr(feature = "benchmarks", feature(test))]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
 
 //! A tiny library to efficiently search strings for sets of ASCII
 //! characters or byte slices for sets of bytes.
@@ -32,6 +33,58 @@
 //! }
 //! ```
 //!
+//! ### Searching for a set of characters known only at runtime
+//!
+//! ```
+//! use jetscii::AsciiChars;
+//!
+//! let delimiters = String::from(";|");
+//! let first = AsciiChars::from_str(&delimiters).find("a|b;c");
+//! assert_eq!(first, Some(1));
+//! ```
+//!
+//! ### Skipping over a set of ASCII characters
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate jetscii;
+//!
+//! fn main() {
+//!     let line = "  \t let x = 1;";
+//!     let indent = ascii_chars!(' ', '\t').span(line);
+//!     assert_eq!(indent, 4);
+//!
+//!     let first = ascii_chars!(^ ' ', '\t').find(line);
+//!     assert_eq!(first, Some(4));
+//! }
+//! ```
+//!
+//! ### Searching for ranges of ASCII characters
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate jetscii;
+//!
+//! fn main() {
+//!     let identifier = "snake_case2";
+//!     let alphanumeric = ascii_ranges!('a'..='z', 'A'..='Z', '0'..='9');
+//!     assert_eq!(alphanumeric.find_not(identifier), Some(5));
+//!
+//!     let control = ascii_ranges!('\0'..='\x1F', '\x7F'..='\x7F');
+//!     assert_eq!(control.find("tab\there"), Some(3));
+//! }
+//! ```
+//!
+//! ### Searching for a large set of bytes
+//!
+//! ```
+//! use jetscii::ByteSet;
+//!
+//! let json_special = ByteSet::new(b"{}[]:,\"\\\x00\x01\x02\x03\x04\x05\x06\x07\x08\t\n\r");
+//! let first = json_special.find(br#"name": "value""#);
+//! assert_eq!(first, Some(4));
+//! ```
+//!
 //! ### Searching for a substring
 //!
 //! ```
@@ -52,6 +105,27 @@
 //! assert_eq!(first, Some(2));
 //! ```
 //!
+//! ### Building searchers at compile time
+//!
+//! The constructors are `const fn`s, so searchers can be stored in a
+//! `static` without any lazy initialization.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate jetscii;
+//!
+//! use jetscii::{AsciiCharsConst, Substring, SubstringConst};
+//!
+//! static XML_DELIMITERS: AsciiCharsConst = ascii_chars!('<', '>', '&');
+//! static CDATA_START: SubstringConst = Substring::new("<![CDATA[");
+//!
+//! fn main() {
+//!     let xml = "a &amp; <![CDATA[b]]>";
+//!     assert_eq!(XML_DELIMITERS.find(xml), Some(2));
+//!     assert_eq!(CDATA_START.find(xml), Some(8));
+//! }
+//! ```
+//!
 //! ## Using the pattern API
 //!
 //! If this crate is compiled with the unstable `pattern` feature
@@ -89,8 +163,18 @@
 //! and `PCMPESTRM`) to gain great speedups. This method stays fast even
 //! when searching for a byte in a set of up to 16 choices.
 //!
-//! When the `PCMPxSTRx` instructions are not available, we fall back to
-//! reasonably fast but universally-supported methods.
+//! Sets of one to three bytes, and the first byte of a substring, are
+//! instead compared against one byte at a time, which is faster. On
+//! machines with AVX2 these comparisons use 256-bit registers.
+//!
+//! When the `PCMPxSTRx` instructions are not available, x86-64 machines
+//! fall back to SSE2 instructions, which every such machine
+//! supports. Other platforms fall back to reasonably fast but
+//! universally-supported methods.
+//!
+//! If this crate is compiled with the unstable `portable-simd`
+//! feature (requires nightly), other platforms instead use vector
+//! instructions through [`std::simd`].
 //!
 //! ## Benchmarks
 //!
@@ -141,9 +225,6 @@
 //! | **<code>Substring::new("xyzzy").find(s)</code>** | **11475 MB/s** |
 //! | <code>s.find("xyzzy")</code>                     | 5391 MB/s      |
 
-#[cfg(test)]
-#[macro_use]
-extern crate lazy_static;
 #[cfg(test)]
 extern crate memmap;
 #[cfg(test)]
@@ -153,7 +234,174 @@ extern crate region;
 
 use std::marker::PhantomData;
 
-include!(concat!(env!("OUT_DIR"), "/src/macros.rs"));
+/// A convenience constructor for a [`Bytes`] that automatically
+/// implements a fallback. Provide up to 16 bytes in total.
+///
+/// Each argument may be a byte, an ASCII `char`, a string or byte
+/// string, or an inclusive range of bytes or ASCII `char`s. When
+/// every argument is a literal, a set with more than 16 bytes, a
+/// duplicate byte or a non-ASCII `char` fails to compile. Otherwise,
+/// duplicates are ignored and the other problems panic.
+///
+/// Start the list with `^` to construct a [`NotBytes`] instead,
+/// which searches for bytes that are *not* in the set.
+///
+/// ```
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     assert_eq!(bytes!(b"\r\n").find(b"a\nb"), Some(1));
+///     assert_eq!(bytes!(0x00..=0x08, 0x7F).find(b"ab\x7F"), Some(2));
+///
+///     let separator = b';';
+///     assert_eq!(bytes!(separator, b'\n').find(b"a;b"), Some(1));
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     // `\n` appears twice
+///     bytes!(b"\r\n", b'\n');
+/// }
+/// ```
+#[macro_export]
+macro_rules! bytes {
+    (^ $($arg:tt)+) => (
+        $crate::macros::Set::into_not_bytes($crate::__jetscii_set!($crate::macros::Set::bytes(); $($arg)+))
+    );
+    ($($arg:tt)+) => (
+        $crate::macros::Set::into_bytes($crate::__jetscii_set!($crate::macros::Set::bytes(); $($arg)+))
+    );
+}
+
+/// A convenience constructor for an [`AsciiChars`] that automatically
+/// implements a fallback. Provide up to 16 characters in total.
+///
+/// Each argument may be an ASCII `char` or byte, a string or byte
+/// string, or an inclusive range such as `'a'..='f'`. When every
+/// argument is a literal, a set with more than 16 characters, a
+/// duplicate character or a non-ASCII character fails to compile.
+/// Otherwise, duplicates are ignored and the other problems panic.
+///
+/// Start the list with `^` to construct a [`NotAsciiChars`] instead,
+/// which searches for characters that are *not* in the set.
+///
+/// ```
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     assert_eq!(ascii_chars!("<>&\"'").find("a > b"), Some(2));
+///     assert_eq!(ascii_chars!('a'..='f', '0'..='9').find("xyz0"), Some(3));
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     ascii_chars!("é");
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     ascii_chars!('a'..='q');
+/// }
+/// ```
+#[macro_export]
+macro_rules! ascii_chars {
+    (^ $($arg:tt)+) => (
+        $crate::macros::Set::into_not_ascii_chars($crate::__jetscii_set!($crate::macros::Set::ascii(); $($arg)+))
+    );
+    ($($arg:tt)+) => (
+        $crate::macros::Set::into_ascii_chars($crate::__jetscii_set!($crate::macros::Set::ascii(); $($arg)+))
+    );
+}
+
+/// A convenience constructor for a [`ByteRanges`] that automatically
+/// implements a fallback. Provide 1 to 8 inclusive ranges, such as
+/// `0x00..=0x1F`.
+///
+/// When every range is written with literals, a bound that isn't a
+/// byte, a range that ends before it starts or more than 8 ranges
+/// fail to compile. Otherwise, the same problems panic.
+///
+/// ```
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     let high = byte_ranges!(0x80..=0xFF);
+///     assert_eq!(high.find(&[0x00, 0xC8]), Some(1));
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     byte_ranges!(0..=300);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     byte_ranges!(b'z'..=b'a');
+/// }
+/// ```
+#[macro_export]
+macro_rules! byte_ranges {
+    ($($arg:tt)+) => (
+        $crate::macros::Ranges::into_byte_ranges($crate::__jetscii_ranges!($crate::macros::Ranges::bytes(); $($arg)+))
+    );
+}
+
+/// A convenience constructor for an [`AsciiRanges`] that
+/// automatically implements a fallback. Provide 1 to 8 inclusive
+/// ranges, such as `'a'..='z'`.
+///
+/// When every range is written with literals, a non-ASCII bound, a
+/// range that ends before it starts or more than 8 ranges fail to
+/// compile. Otherwise, the same problems panic.
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate jetscii;
+///
+/// fn main() {
+///     ascii_ranges!('\u{100}'..='\u{17F}');
+/// }
+/// ```
+#[macro_export]
+macro_rules! ascii_ranges {
+    ($($arg:tt)+) => (
+        $crate::macros::Ranges::into_ascii_ranges($crate::__jetscii_ranges!($crate::macros::Ranges::ascii(); $($arg)+))
+    );
+}
+
+#[doc(hidden)]
+pub mod macros;
+
+mod error;
+
+pub use error::{ClassErrorKind, Error};
+
+pub mod classes;
+
+mod bracket;
 
 #[cfg(target_arch = "x86_64")]
 mod simd;
@@ -161,14 +409,117 @@ mod simd;
 #[cfg(not(target_feature = "sse4.2"))]
 mod fallback;
 
+#[cfg(target_arch = "x86_64")]
+mod sse2;
+
+#[cfg(target_arch = "x86_64")]
+mod avx2;
+
+#[cfg(target_arch = "x86_64")]
+mod ssse3;
+
+mod frequency;
+
+#[cfg(any(test, all(not(target_arch = "x86_64"), not(feature = "portable-simd"))))]
+mod horspool;
+
+mod table;
+
+mod twoway;
+
+#[cfg(feature = "portable-simd")]
+mod portable;
+
 #[cfg(feature = "pattern")]
 mod pattern;
 
+// `is_x86_feature_detected!` caches its result for the whole process,
+// so each runtime check is a single load.
 macro_rules! dispatch {
     (simd: $simd:expr,fallback: $fallback:expr,) => {
+        dispatch! {
+            feature: "sse4.2",
+            simd: $simd,
+            fallback: $fallback,
+        }
+    };
+
+    (simd: $simd:expr,sse2: $sse2:expr,fallback: $fallback:expr,) => {
+        dispatch! {
+            simd: $simd,
+            sse2: $sse2,
+            portable: $fallback,
+            fallback: $fallback,
+        }
+    };
+
+    (simd: $simd:expr,sse2: $sse2:expr,portable: $portable:expr,fallback: $fallback:expr,) => {
+        #[cfg(target_feature = "sse4.2")]
+        {
+            $simd
+        }
+
+        // Other platforms only get vector instructions through
+        // `std::simd`, which requires nightly.
+        #[cfg(all(not(target_arch = "x86_64"), feature = "portable-simd"))]
+        {
+            $portable
+        }
+
+        #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+        {
+            $fallback
+        }
+
+        // Every x86_64 machine has SSE2, so there's no need to check
+        // for it.
+        #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+        {
+            if is_x86_feature_detected!("sse4.2") {
+                $simd
+            } else {
+                $sse2
+            }
+        }
+    };
+
+    (avx2: $avx2:expr,simd: $simd:expr,sse2: $sse2:expr,portable: $portable:expr,fallback: $fallback:expr,) => {
+        #[cfg(target_feature = "avx2")]
+        {
+            $avx2
+        }
+
+        #[cfg(all(not(target_arch = "x86_64"), feature = "portable-simd"))]
+        {
+            $portable
+        }
+
+        #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+        {
+            $fallback
+        }
+
+        // Without AVX2, pick between the 128-bit implementations as
+        // usual.
+        #[cfg(all(target_arch = "x86_64", not(target_feature = "avx2")))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                $avx2
+            } else {
+                dispatch! {
+                    simd: $simd,
+                    sse2: $sse2,
+                    portable: $portable,
+                    fallback: $fallback,
+                }
+            }
+        }
+    };
+
+    (feature: $feature:tt,simd: $simd:expr,fallback: $fallback:expr,) => {
         // If we can tell at compile time that we have support,
         // call the optimized code directly.
-        #[cfg(target_feature = "sse4.2")]
+        #[cfg(target_feature = $feature)]
         {
             $simd
         }
@@ -182,61 +533,1048 @@ macro_rules! dispatch {
 
         // Otherwise, we will be run on a machine with or without
         // support, so we perform runtime detection.
-        #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+        #[cfg(all(target_arch = "x86_64", not(target_feature = $feature)))]
         {
-            if is_x86_feature_detected!("sse4.2") {
+            if is_x86_feature_detected!($feature) {
                 $simd
             } else {
                 $fallback
             }
         }
-    };
+    };
+}
+
+/// Panics if the number of valid bytes doesn't fit in the 16-byte
+/// array, as `PCMPESTRI` would read past it.
+const fn assert_len(len: i32) {
+    assert!(len >= 0, "Cannot have a negative length");
+    assert!(len <= 16, "Cannot have more than 16 bytes");
+}
+
+/// Panics if any of the bytes is not ASCII. This is a loop instead
+/// of an iterator so that it can be used in a `const fn`.
+const fn assert_ascii(bytes: &[u8; 16]) {
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i] < 128, "Cannot have non-ASCII bytes");
+        i += 1;
+    }
+}
+
+/// Copies the bytes into the 16-byte array that the SIMD
+/// implementations compare against. More than 16 bytes fail to
+/// compile.
+const fn padded<const N: usize>(bytes: [u8; N]) -> [u8; 16] {
+    let () = AtMost16::<N>::CHECK;
+
+    let mut array = [0; 16];
+    let mut i = 0;
+    while i < N {
+        array[i] = bytes[i];
+        i += 1;
+    }
+    array
+}
+
+struct AtMost16<const N: usize>;
+
+impl<const N: usize> AtMost16<N> {
+    // Evaluated when `padded` is instantiated, so this fails at
+    // compile time rather than when a searcher is constructed.
+    const CHECK: () = assert!(N <= 16, "Cannot have more than 16 bytes");
+}
+
+/// Sets of up to this many bytes are faster to search by comparing
+/// against each byte in turn than by using `PCMPESTRI`.
+#[cfg(target_arch = "x86_64")]
+const TINY_SET_MAX_LEN: i32 = 3;
+
+/// Searches a slice for a set of bytes. Up to 16 bytes may be used.
+///
+/// On x86_64, sets of one to three bytes are searched by comparing
+/// against each byte in turn, which is faster than `PCMPESTRI` for so few bytes.
+pub struct Bytes<F>
+where
+    F: Fn(u8) -> bool,
+{
+    // Include this implementation only when compiling for x86_64 as
+    // that's the only platform that we support.
+    #[cfg(target_arch = "x86_64")]
+    simd: simd::Bytes,
+
+    // The AVX2, SSE2 and portable implementations compare against
+    // each of these bytes in turn.
+    #[cfg(any(target_arch = "x86_64", feature = "portable-simd"))]
+    list: table::ByteList,
+
+    #[cfg(target_arch = "x86_64")]
+    tiny: bool,
+
+    // The members of the set, which the SIMD implementations can't
+    // be asked about. Otherwise, the fallback's table is used.
+    #[cfg(target_feature = "sse4.2")]
+    set: table::ByteTable,
+
+    // If we are *guaranteed* to have SSE 4.2, then there's no reason
+    // to have this implementation.
+    #[cfg(not(target_feature = "sse4.2"))]
+    fallback: fallback::Bytes<F>,
+
+    // The closure is never called then, but a `const fn` can't drop
+    // it, so it is kept until the searcher is dropped.
+    #[cfg(target_feature = "sse4.2")]
+    _fallback: Option<F>,
+}
+
+impl<F> Bytes<F>
+where
+    F: Fn(u8) -> bool,
+{
+    /// Manual constructor; prefer using [`bytes!`] instead.
+    ///
+    /// Provide an array of bytes to search for, the number of
+    /// valid bytes provided, and a closure to use when the SIMD
+    /// intrinsics are not available. The closure **must** search for
+    /// the same bytes as in the array.
+    ///
+    /// ### Panics
+    ///
+    /// - If `len` is negative or greater than 16.
+    pub const fn new(bytes: [u8; 16], len: i32, fallback: F) -> Self {
+        assert_len(len);
+        Bytes::with_fallback(bytes, len, Some(fallback))
+    }
+
+    /// Like [`Bytes::new`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ```
+    /// use jetscii::{Bytes, Error};
+    ///
+    /// let too_long = Bytes::try_new([0; 16], 17, |_| false);
+    /// assert_eq!(too_long.err(), Some(Error::TooManyBytes { len: 17 }));
+    /// ```
+    pub fn try_new(bytes: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        error::check_len(len)?;
+        Ok(Bytes::with_fallback(bytes, len, Some(fallback)))
+    }
+
+    /// Without a closure, the fallback implementation looks the bytes
+    /// up in a table.
+    const fn with_fallback(bytes: [u8; 16], len: i32, fallback: Option<F>) -> Self {
+        Bytes {
+            #[cfg(target_arch = "x86_64")]
+            simd: simd::Bytes::new(bytes, len),
+
+            #[cfg(any(target_arch = "x86_64", feature = "portable-simd"))]
+            list: table::ByteList::new(bytes, len as usize),
+
+            #[cfg(target_arch = "x86_64")]
+            tiny: len <= TINY_SET_MAX_LEN,
+
+            #[cfg(target_feature = "sse4.2")]
+            set: table::ByteTable::from_array(bytes, len as usize),
+
+            #[cfg(not(target_feature = "sse4.2"))]
+            fallback: fallback::Bytes::with_bytes(bytes, len, fallback),
+
+            #[cfg(target_feature = "sse4.2")]
+            _fallback: fallback,
+        }
+    }
+
+    /// Searches the slice for the first matching byte in the set.
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.tiny {
+                return self.find_tiny(haystack);
+            }
+        }
+
+        dispatch! {
+            simd: unsafe { self.simd.find(haystack) },
+            sse2: unsafe { self.sse2().find(haystack) },
+            portable: self.portable().find(haystack),
+            fallback: self.fallback.find(haystack),
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn avx2(&self) -> avx2::Bytes<'_> {
+        avx2::Bytes::new(&self.list)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn sse2(&self) -> sse2::Bytes<'_> {
+        sse2::Bytes::new(&self.list)
+    }
+
+    #[cfg(all(not(target_arch = "x86_64"), feature = "portable-simd"))]
+    fn portable(&self) -> portable::Bytes<'_> {
+        portable::Bytes::new(&self.list)
+    }
+
+    /// Compares against each byte of a tiny set in turn, using the
+    /// widest registers available.
+    #[cfg(target_arch = "x86_64")]
+    #[inline]
+    fn find_tiny(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            feature: "avx2",
+            simd: unsafe { self.avx2().find(haystack) },
+            fallback: unsafe { self.sse2().find(haystack) },
+        }
+    }
+
+    /// Searches the slice for the last matching byte in the set.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.tiny {
+                return unsafe { self.sse2().rfind(haystack) };
+            }
+        }
+
+        dispatch! {
+            simd: unsafe { self.simd.rfind(haystack) },
+            sse2: unsafe { self.sse2().rfind(haystack) },
+            portable: self.portable().rfind(haystack),
+            fallback: self.fallback.rfind(haystack),
+        }
+    }
+
+    /// Searches the slice for the first byte that is *not* in the set.
+    #[inline]
+    pub fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.tiny {
+                return unsafe { self.sse2().find_not(haystack) };
+            }
+        }
+
+        dispatch! {
+            simd: unsafe { self.simd.find_not(haystack) },
+            sse2: unsafe { self.sse2().find_not(haystack) },
+            portable: self.portable().find_not(haystack),
+            fallback: self.fallback.find_not(haystack),
+        }
+    }
+
+    /// Searches the slice for the last byte that is *not* in the set.
+    #[inline]
+    pub fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.tiny {
+                return unsafe { self.sse2().rfind_not(haystack) };
+            }
+        }
+
+        dispatch! {
+            simd: unsafe { self.simd.rfind_not(haystack) },
+            sse2: unsafe { self.sse2().rfind_not(haystack) },
+            portable: self.portable().rfind_not(haystack),
+            fallback: self.fallback.rfind_not(haystack),
+        }
+    }
+
+    /// Returns the length of the leading run of bytes that are in
+    /// the set, like C's `strspn`.
+    #[inline]
+    pub fn span(&self, haystack: &[u8]) -> usize {
+        self.find_not(haystack).unwrap_or(haystack.len())
+    }
+
+    /// Returns `true` if the byte is in the set.
+    pub fn contains(&self, b: u8) -> bool {
+        self.table().contains(b)
+    }
+
+    /// Returns an iterator over the bytes in the set, in ascending
+    /// order.
+    pub fn iter(&self) -> Members {
+        Members::new(self.table().clone())
+    }
+
+    /// Constructs the set of bytes that are in either set.
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate jetscii;
+    ///
+    /// fn main() {
+    ///     let line_ends = bytes!(b'\r').union(&bytes!(b'\n')).unwrap();
+    ///     assert_eq!(line_ends.iter().collect::<Vec<_>>(), b"\n\r");
+    /// }
+    /// ```
+    ///
+    /// ### Errors
+    ///
+    /// - If the union has more than 16 bytes.
+    pub fn union<G>(&self, other: &Bytes<G>) -> Result<BytesConst, Error>
+    where
+        G: Fn(u8) -> bool,
+    {
+        Bytes::try_from_slice(&self.table().union(other.table()).bytes())
+    }
+
+    /// Constructs the set of bytes that are in both sets.
+    pub fn intersection<G>(&self, other: &Bytes<G>) -> BytesConst
+    where
+        G: Fn(u8) -> bool,
+    {
+        Bytes::from_slice(&self.table().intersection(other.table()).bytes())
+    }
+
+    /// Constructs the set of bytes that are in this set but not the
+    /// other.
+    pub fn difference<G>(&self, other: &Bytes<G>) -> BytesConst
+    where
+        G: Fn(u8) -> bool,
+    {
+        Bytes::from_slice(&self.table().difference(other.table()).bytes())
+    }
+
+    /// Constructs a searcher for every byte that is *not* in the set.
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate jetscii;
+    ///
+    /// fn main() {
+    ///     let digits = bytes!(b'0'..=b'9');
+    ///     assert!(digits.contains(b'7'));
+    ///     assert_eq!(digits.complement().find(b"42px"), Some(2));
+    /// }
+    /// ```
+    pub fn complement(&self) -> NotBytesConst {
+        NotBytes(Bytes::from_slice(&self.table().bytes()))
+    }
+
+    fn iter_complement(&self) -> Members {
+        Members::new(self.table().complement())
+    }
+
+    fn table(&self) -> &table::ByteTable {
+        #[cfg(target_feature = "sse4.2")]
+        {
+            &self.set
+        }
+
+        #[cfg(not(target_feature = "sse4.2"))]
+        {
+            self.fallback.table()
+        }
+    }
+
+    /// Returns an iterator over the positions of every matching byte
+    /// in the slice. The iterator can be consumed from either end.
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h [u8]) -> FindIter<'a, 'h, F> {
+        FindIter {
+            #[cfg(target_arch = "x86_64")]
+            simd: self.simd.find_iter(haystack),
+
+            #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+            sse2: self.sse2().find_iter(haystack),
+
+            #[cfg(not(target_arch = "x86_64"))]
+            fallback: self.fallback.find_iter(haystack),
+
+            _fallback: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the bytes in a set, in ascending order.
+///
+/// Created by [`Bytes::iter`] and [`AsciiChars::iter`].
+pub struct Members {
+    set: table::ByteTable,
+    next: usize,
+}
+
+impl Members {
+    fn new(set: table::ByteTable) -> Self {
+        Members { set, next: 0 }
+    }
+}
+
+impl Iterator for Members {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.next < 256 {
+            let b = self.next as u8;
+            self.next += 1;
+            if self.set.contains(b) {
+                return Some(b);
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the positions of every matching byte.
+///
+/// Created by [`Bytes::find_iter`] and [`AsciiChars::find_iter`].
+pub struct FindIter<'a, 'h, F>
+where
+    F: Fn(u8) -> bool,
+{
+    #[cfg(target_arch = "x86_64")]
+    simd: simd::FindIter<'a, 'h>,
+
+    #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+    sse2: sse2::FindIter<'a, 'h>,
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fallback: fallback::FindIter<'a, 'h, F>,
+
+    _fallback: PhantomData<F>,
+}
+
+impl<'a, 'h, F> Iterator for FindIter<'a, 'h, F>
+where
+    F: Fn(u8) -> bool,
+{
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        dispatch! {
+            simd: unsafe { self.simd.next() },
+            sse2: unsafe { self.sse2.next() },
+            fallback: self.fallback.next(),
+        }
+    }
+}
+
+impl<'a, 'h, F> DoubleEndedIterator for FindIter<'a, 'h, F>
+where
+    F: Fn(u8) -> bool,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        dispatch! {
+            simd: unsafe { self.simd.next_back() },
+            sse2: unsafe { self.sse2.next_back() },
+            fallback: self.fallback.next_back(),
+        }
+    }
+}
+
+/// A convenience type that can be used in a constant or static.
+pub type BytesConst = Bytes<fn(u8) -> bool>;
+
+impl BytesConst {
+    /// Constructs a set of up to 16 bytes without a fallback
+    /// closure; [`bytes!`] expands to this.
+    ///
+    /// ```
+    /// use jetscii::{Bytes, BytesConst};
+    ///
+    /// static DELIMITERS: BytesConst = Bytes::from_array(*b"\t;|");
+    /// assert_eq!(DELIMITERS.find(b"key;value"), Some(3));
+    /// ```
+    ///
+    /// More than 16 bytes fail to compile:
+    ///
+    /// ```compile_fail
+    /// let too_many = jetscii::Bytes::from_array([0; 17]);
+    /// ```
+    pub const fn from_array<const N: usize>(bytes: [u8; N]) -> Self {
+        Bytes::with_fallback(padded(bytes), N as i32, None)
+    }
+
+    /// Constructs a set of bytes that is only known at runtime, such
+    /// as one read from a configuration file. No fallback closure is
+    /// needed.
+    ///
+    /// ```
+    /// use jetscii::Bytes;
+    ///
+    /// let delimiters = Bytes::from_slice(b"\t;|");
+    /// assert_eq!(delimiters.find(b"key;value"), Some(3));
+    /// ```
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide more than 16 bytes.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        assert!(bytes.len() <= 16, "Cannot have more than 16 bytes");
+
+        let mut array = [0; 16];
+        array[..bytes.len()].copy_from_slice(bytes);
+        Bytes::with_fallback(array, bytes.len() as i32, None)
+    }
+
+    /// Like [`Bytes::from_slice`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ```
+    /// use jetscii::{Bytes, Error};
+    ///
+    /// let too_many = Bytes::try_from_slice(b"abcdefghijklmnopq");
+    /// assert_eq!(too_many.err(), Some(Error::TooManyBytes { len: 17 }));
+    /// ```
+    pub fn try_from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() > 16 {
+            return Err(Error::TooManyBytes { len: bytes.len() });
+        }
+        Ok(Bytes::from_slice(bytes))
+    }
+
+    /// Parses a bracket expression, such as `[a-z0-9_-]`, into the
+    /// fastest searcher for that class; see [`BracketClass`].
+    pub fn parse_class(expr: &str) -> Result<BracketClass, Error> {
+        expr.parse()
+    }
+}
+
+/// Searches a string for a set of ASCII characters. Up to 16
+/// characters may be used.
+pub struct AsciiChars<F>(Bytes<F>)
+where
+    F: Fn(u8) -> bool;
+
+impl<F> AsciiChars<F>
+where
+    F: Fn(u8) -> bool,
+{
+    /// Manual constructor; prefer using [`ascii_chars!`] instead.
+    ///
+    /// Provide an array of ASCII bytes to search for, the number of
+    /// valid bytes provided, and a closure to use when the SIMD
+    /// intrinsics are not available. The closure **must** search for
+    /// the same characters as in the array.
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII byte.
+    /// - If `len` is negative or greater than 16.
+    pub const fn new(chars: [u8; 16], len: i32, fallback: F) -> Self {
+        assert_ascii(&chars);
+        AsciiChars(Bytes::new(chars, len, fallback))
+    }
+
+    /// Like [`AsciiChars::new`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ```
+    /// use jetscii::{AsciiChars, Error};
+    ///
+    /// let mut chars = [0; 16];
+    /// chars[..3].copy_from_slice("<é".as_bytes());
+    ///
+    /// let non_ascii = AsciiChars::try_new(chars, 3, |_| false);
+    /// assert_eq!(non_ascii.err(), Some(Error::NonAscii { byte: 0xC3, index: 1 }));
+    /// ```
+    pub fn try_new(chars: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        error::check_len(len)?;
+        error::check_ascii(&chars[..len as usize])?;
+        Ok(AsciiChars(Bytes::with_fallback(chars, len, Some(fallback))))
+    }
+
+    /// Searches the string for the first matching ASCII byte in the set.
+    #[inline]
+    pub fn find(&self, haystack: &str) -> Option<usize> {
+        self.0.find(haystack.as_bytes())
+    }
+
+    /// Searches the string for the last matching ASCII byte in the set.
+    #[inline]
+    pub fn rfind(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind(haystack.as_bytes())
+    }
+
+    /// Searches the string for the first byte that is *not* in the set.
+    #[inline]
+    pub fn find_not(&self, haystack: &str) -> Option<usize> {
+        self.0.find_not(haystack.as_bytes())
+    }
+
+    /// Searches the string for the last byte that is *not* in the set.
+    #[inline]
+    pub fn rfind_not(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind_not(haystack.as_bytes())
+    }
+
+    /// Returns the length of the leading run of characters that are
+    /// in the set, like C's `strspn`.
+    #[inline]
+    pub fn span(&self, haystack: &str) -> usize {
+        self.0.span(haystack.as_bytes())
+    }
+
+    /// Returns an iterator over the positions of every matching
+    /// ASCII byte in the string. The iterator can be consumed from
+    /// either end.
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h str) -> FindIter<'a, 'h, F> {
+        self.0.find_iter(haystack.as_bytes())
+    }
+
+    /// Returns `true` if the ASCII byte is in the set.
+    pub fn contains(&self, b: u8) -> bool {
+        self.0.contains(b)
+    }
+
+    /// Returns an iterator over the ASCII bytes in the set, in
+    /// ascending order.
+    pub fn iter(&self) -> Members {
+        self.0.iter()
+    }
+
+    /// Constructs the set of characters that are in either set.
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate jetscii;
+    ///
+    /// use jetscii::classes::XML_SPECIAL;
+    ///
+    /// fn main() {
+    ///     let delimiters = XML_SPECIAL
+    ///         .union(&ascii_chars!('\n'))
+    ///         .unwrap()
+    ///         .difference(&ascii_chars!('\''));
+    ///     assert_eq!(delimiters.find("it's\n"), Some(4));
+    /// }
+    /// ```
+    ///
+    /// ### Errors
+    ///
+    /// - If the union has more than 16 characters.
+    pub fn union<G>(&self, other: &AsciiChars<G>) -> Result<AsciiCharsConst, Error>
+    where
+        G: Fn(u8) -> bool,
+    {
+        self.0.union(&other.0).map(AsciiChars)
+    }
+
+    /// Constructs the set of characters that are in both sets.
+    pub fn intersection<G>(&self, other: &AsciiChars<G>) -> AsciiCharsConst
+    where
+        G: Fn(u8) -> bool,
+    {
+        AsciiChars(self.0.intersection(&other.0))
+    }
+
+    /// Constructs the set of characters that are in this set but not
+    /// the other.
+    pub fn difference<G>(&self, other: &AsciiChars<G>) -> AsciiCharsConst
+    where
+        G: Fn(u8) -> bool,
+    {
+        AsciiChars(self.0.difference(&other.0))
+    }
+
+    /// Constructs a searcher for every character that is *not* in
+    /// the set.
+    pub fn complement(&self) -> NotAsciiCharsConst {
+        NotAsciiChars(AsciiChars(self.0.complement().0))
+    }
+}
+
+/// A convenience type that can be used in a constant or static.
+pub type AsciiCharsConst = AsciiChars<fn(u8) -> bool>;
+
+impl AsciiCharsConst {
+    /// Constructs a set of up to 16 ASCII characters without a
+    /// fallback closure; [`ascii_chars!`] expands to this.
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII byte.
+    pub const fn from_array<const N: usize>(chars: [u8; N]) -> Self {
+        let chars = padded(chars);
+        assert_ascii(&chars);
+        AsciiChars(Bytes::with_fallback(chars, N as i32, None))
+    }
+
+    /// Constructs a set of ASCII characters that is only known at
+    /// runtime, such as one read from a configuration file. No
+    /// fallback closure is needed.
+    ///
+    /// ```
+    /// use jetscii::AsciiChars;
+    ///
+    /// let delimiters = AsciiChars::from_str("<>&");
+    /// assert_eq!(delimiters.find("a < b"), Some(2));
+    /// ```
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII character.
+    /// - If you provide more than 16 characters.
+    // This panics instead of returning an error, so it can't be
+    // `FromStr::from_str`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(chars: &str) -> Self {
+        assert!(chars.is_ascii(), "Cannot have non-ASCII bytes");
+        AsciiChars(Bytes::from_slice(chars.as_bytes()))
+    }
+
+    /// Like [`AsciiChars::from_str`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ```
+    /// use jetscii::{AsciiChars, Error};
+    ///
+    /// let non_ascii = AsciiChars::try_from_str("<é>");
+    /// assert_eq!(non_ascii.err(), Some(Error::NonAscii { byte: 0xC3, index: 1 }));
+    /// ```
+    pub fn try_from_str(chars: &str) -> Result<Self, Error> {
+        error::check_ascii(chars.as_bytes())?;
+        Bytes::try_from_slice(chars.as_bytes()).map(AsciiChars)
+    }
+}
+
+/// Searches a slice for any byte that is *not* in a set of
+/// bytes. Up to 16 bytes may be used.
+pub struct NotBytes<F>(Bytes<F>)
+where
+    F: Fn(u8) -> bool;
+
+impl<F> NotBytes<F>
+where
+    F: Fn(u8) -> bool,
+{
+    /// Manual constructor; prefer using [`bytes!`] with a leading
+    /// `^` instead.
+    ///
+    /// The arguments are the same as for [`Bytes::new`]; the closure
+    /// must match the bytes *in* the set.
+    pub const fn new(bytes: [u8; 16], len: i32, fallback: F) -> Self {
+        NotBytes(Bytes::new(bytes, len, fallback))
+    }
+
+    /// Like [`NotBytes::new`], but returns an error instead of
+    /// panicking.
+    pub fn try_new(bytes: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        Bytes::try_new(bytes, len, fallback).map(NotBytes)
+    }
+
+    /// Searches the slice for the first byte that is not in the set.
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.0.find_not(haystack)
+    }
+
+    /// Searches the slice for the last byte that is not in the set.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.0.rfind_not(haystack)
+    }
+
+    /// Returns `true` if the byte is *not* in the set, which is
+    /// when `find` would match it.
+    pub fn contains(&self, b: u8) -> bool {
+        !self.0.contains(b)
+    }
+
+    /// Returns an iterator over the bytes that are *not* in the set,
+    /// in ascending order.
+    pub fn iter(&self) -> Members {
+        self.0.iter_complement()
+    }
+}
+
+/// A convenience type that can be used in a constant or static.
+pub type NotBytesConst = NotBytes<fn(u8) -> bool>;
+
+impl NotBytesConst {
+    /// Constructs a set of up to 16 bytes without a fallback
+    /// closure; [`bytes!`] with a leading `^` expands to this.
+    pub const fn from_array<const N: usize>(bytes: [u8; N]) -> Self {
+        NotBytes(Bytes::from_array(bytes))
+    }
+}
+
+/// Searches a string for any character that is *not* in a set of
+/// ASCII characters. Up to 16 characters may be used.
+pub struct NotAsciiChars<F>(AsciiChars<F>)
+where
+    F: Fn(u8) -> bool;
+
+impl<F> NotAsciiChars<F>
+where
+    F: Fn(u8) -> bool,
+{
+    /// Manual constructor; prefer using [`ascii_chars!`] with a
+    /// leading `^` instead.
+    ///
+    /// The arguments are the same as for [`AsciiChars::new`]; the
+    /// closure must match the characters *in* the set.
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII byte.
+    pub const fn new(chars: [u8; 16], len: i32, fallback: F) -> Self {
+        NotAsciiChars(AsciiChars::new(chars, len, fallback))
+    }
+
+    /// Like [`NotAsciiChars::new`], but returns an error instead of
+    /// panicking.
+    pub fn try_new(chars: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        AsciiChars::try_new(chars, len, fallback).map(NotAsciiChars)
+    }
+
+    /// Searches the string for the first character that is not in
+    /// the set.
+    #[inline]
+    pub fn find(&self, haystack: &str) -> Option<usize> {
+        self.0.find_not(haystack)
+    }
+
+    /// Searches the string for the last character that is not in the
+    /// set.
+    #[inline]
+    pub fn rfind(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind_not(haystack)
+    }
+
+    /// Returns `true` if the byte is *not* in the set, which is
+    /// when `find` would match it. Every non-ASCII byte is contained.
+    pub fn contains(&self, b: u8) -> bool {
+        !self.0.contains(b)
+    }
+
+    /// Returns an iterator over the bytes that are *not* in the set,
+    /// in ascending order, including every non-ASCII byte.
+    pub fn iter(&self) -> Members {
+        self.0 .0.iter_complement()
+    }
+}
+
+/// A convenience type that can be used in a constant or static.
+pub type NotAsciiCharsConst = NotAsciiChars<fn(u8) -> bool>;
+
+impl NotAsciiCharsConst {
+    /// Constructs a set of up to 16 ASCII characters without a
+    /// fallback closure; [`ascii_chars!`] with a leading `^` expands
+    /// to this.
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII byte.
+    pub const fn from_array<const N: usize>(chars: [u8; N]) -> Self {
+        NotAsciiChars(AsciiChars::from_array(chars))
+    }
+}
+
+/// Searches a slice for a set of bytes. Any number of bytes may be
+/// used.
+///
+/// Small sets are packed into several SIMD comparisons per chunk of
+/// the haystack; larger sets use a [`ByteClass`].
+pub struct ByteSet {
+    strategy: ByteSetStrategy,
+    class: ByteClass,
+}
+
+enum ByteSetStrategy {
+    #[cfg(target_arch = "x86_64")]
+    Packed(simd::ByteSet),
+    Class,
+}
+
+impl ByteSet {
+    /// Constructs a set of the provided bytes. Duplicate bytes are
+    /// ignored.
+    pub fn new(bytes: &[u8]) -> Self {
+        let table = table::ByteTable::new(bytes);
+
+        let strategy = match table.count() {
+            #[cfg(target_arch = "x86_64")]
+            n if n <= simd::BYTE_SET_MAX_LEN => ByteSetStrategy::Packed(simd::ByteSet::new(&table.bytes())),
+            _ => ByteSetStrategy::Class,
+        };
+
+        ByteSet {
+            strategy,
+            class: ByteClass::from_table(table),
+        }
+    }
+
+    /// Searches the slice for the first matching byte in the set.
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        match self.strategy {
+            #[cfg(target_arch = "x86_64")]
+            ByteSetStrategy::Packed(ref packed) => {
+                dispatch! {
+                    simd: unsafe { packed.find(haystack) },
+                    fallback: self.class.find(haystack),
+                }
+            }
+            ByteSetStrategy::Class => self.class.find(haystack),
+        }
+    }
+
+    /// Searches the slice for the last matching byte in the set.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        match self.strategy {
+            #[cfg(target_arch = "x86_64")]
+            ByteSetStrategy::Packed(ref packed) => {
+                dispatch! {
+                    simd: unsafe { packed.rfind(haystack) },
+                    fallback: self.class.rfind(haystack),
+                }
+            }
+            ByteSetStrategy::Class => self.class.rfind(haystack),
+        }
+    }
+}
+
+/// Searches a slice for any byte in an arbitrary class of bytes.
+///
+/// The class is stored as a table with an entry for each of the 256
+/// possible bytes, so any number of bytes may be used. On x86_64, the
+/// table is checked 16 bytes at a time using SSSE3.
+pub struct ByteClass {
+    // Include this implementation only when compiling for x86_64 as
+    // that's the only platform that we support.
+    #[cfg(target_arch = "x86_64")]
+    ssse3: ssse3::ByteClass,
+
+    // If we are *guaranteed* to have SSSE3, then there's no reason
+    // to have this implementation.
+    #[cfg(not(target_feature = "ssse3"))]
+    table: table::ByteTable,
+}
+
+impl ByteClass {
+    /// Constructs a class of the provided bytes. Duplicate bytes are
+    /// ignored.
+    pub fn new(bytes: &[u8]) -> Self {
+        ByteClass::from_table(table::ByteTable::new(bytes))
+    }
+
+    /// Constructs a class of every byte for which the predicate
+    /// returns `true`.
+    ///
+    /// ```
+    /// use jetscii::ByteClass;
+    ///
+    /// let separator = ByteClass::from_fn(|b| b.is_ascii_punctuation() || b.is_ascii_whitespace());
+    /// assert_eq!(separator.find(b"Content-Type: text/plain"), Some(7));
+    /// ```
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: Fn(u8) -> bool,
+    {
+        let bytes: Vec<_> = (0..=255).filter(|&b| f(b)).collect();
+        ByteClass::new(&bytes)
+    }
+
+    fn from_table(table: table::ByteTable) -> Self {
+        ByteClass {
+            #[cfg(target_arch = "x86_64")]
+            ssse3: ssse3::ByteClass::new(&table),
+
+            #[cfg(not(target_feature = "ssse3"))]
+            table,
+        }
+    }
+
+    /// Searches the slice for the first byte in the class.
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            feature: "ssse3",
+            simd: unsafe { self.ssse3.find(haystack) },
+            fallback: haystack.iter().position(|&b| self.table.contains(b)),
+        }
+    }
+
+    /// Searches the slice for the last byte in the class.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            feature: "ssse3",
+            simd: unsafe { self.ssse3.rfind(haystack) },
+            fallback: haystack.iter().rposition(|&b| self.table.contains(b)),
+        }
+    }
 }
 
-/// Searches a slice for a set of bytes. Up to 16 bytes may be used.
-pub struct Bytes<F>
+/// Searches a slice for a byte in a set of inclusive ranges. Up to 8
+/// ranges may be used.
+pub struct ByteRanges<F>
 where
     F: Fn(u8) -> bool,
 {
     // Include this implementation only when compiling for x86_64 as
     // that's the only platform that we support.
     #[cfg(target_arch = "x86_64")]
-    simd: simd::Bytes,
+    simd: simd::ByteRanges,
 
     // If we are *guaranteed* to have SSE 4.2, then there's no reason
-    // to have this implementation.
+    // to have this implementation. A set of ranges is just a set of
+    // bytes to the fallback closure.
     #[cfg(not(target_feature = "sse4.2"))]
     fallback: fallback::Bytes<F>,
 
-    // Since we might not use the fallback implementation, we add this
-    // to avoid unused type parameters.
-    _fallback: PhantomData<F>,
+    // Kept for the same reason as in `Bytes`.
+    #[cfg(target_feature = "sse4.2")]
+    _fallback: Option<F>,
 }
 
-impl<F> Bytes<F>
+impl<F> ByteRanges<F>
 where
     F: Fn(u8) -> bool,
 {
-    /// Manual constructor; prefer using [`bytes!`] instead.
+    /// Manual constructor; prefer using [`byte_ranges!`] instead.
     ///
-    /// Provide an array of bytes to search for, the number of
-    /// valid bytes provided, and a closure to use when the SIMD
-    /// intrinsics are not available. The closure **must** search for
-    /// the same bytes as in the array.
-    #[allow(unused_variables)]
-    pub /* const */ fn new(bytes: [u8; 16], len: i32, fallback: F) -> Self {
-        Bytes {
+    /// Provide an array of ranges to search for, each as a pair of
+    /// the low and high bytes, the number of valid bytes provided
+    /// (two per range), and a closure to use when the SIMD intrinsics
+    /// are not available. The closure **must** search for the same
+    /// ranges as in the array.
+    ///
+    /// ### Panics
+    ///
+    /// - If `len` is negative or greater than 16.
+    pub const fn new(ranges: [u8; 16], len: i32, fallback: F) -> Self {
+        assert_len(len);
+        ByteRanges::with_fallback(ranges, len, Some(fallback))
+    }
+
+    /// Like [`ByteRanges::new`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ```
+    /// use jetscii::{ByteRanges, Error};
+    ///
+    /// let negative = ByteRanges::try_new([0; 16], -2, |_| false);
+    /// assert_eq!(negative.err(), Some(Error::NegativeLength { len: -2 }));
+    /// ```
+    pub fn try_new(ranges: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        error::check_len(len)?;
+        Ok(ByteRanges::with_fallback(ranges, len, Some(fallback)))
+    }
+
+    /// Without a closure, the fallback implementation looks the bytes
+    /// up in a table.
+    const fn with_fallback(ranges: [u8; 16], len: i32, fallback: Option<F>) -> Self {
+        ByteRanges {
             #[cfg(target_arch = "x86_64")]
-            simd: simd::Bytes::new(bytes, len),
+            simd: simd::ByteRanges::new(ranges, len),
 
             #[cfg(not(target_feature = "sse4.2"))]
-            fallback: fallback::Bytes::new(fallback),
+            fallback: fallback::Bytes::with_ranges(ranges, len, fallback),
 
-            _fallback: PhantomData,
+            #[cfg(target_feature = "sse4.2")]
+            _fallback: fallback,
         }
     }
 
-    /// Searches the slice for the first matching byte in the set.
+    /// Searches the slice for the first byte in any of the ranges.
     #[inline]
     pub fn find(&self, haystack: &[u8]) -> Option<usize> {
         dispatch! {
@@ -244,76 +1582,341 @@ where
             fallback: self.fallback.find(haystack),
         }
     }
+
+    /// Searches the slice for the last byte in any of the ranges.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            simd: unsafe { self.simd.rfind(haystack) },
+            fallback: self.fallback.rfind(haystack),
+        }
+    }
+
+    /// Searches the slice for the first byte that is *not* in any of
+    /// the ranges.
+    #[inline]
+    pub fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            simd: unsafe { self.simd.find_not(haystack) },
+            fallback: self.fallback.find_not(haystack),
+        }
+    }
+
+    /// Searches the slice for the last byte that is *not* in any of
+    /// the ranges.
+    #[inline]
+    pub fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        dispatch! {
+            simd: unsafe { self.simd.rfind_not(haystack) },
+            fallback: self.fallback.rfind_not(haystack),
+        }
+    }
 }
 
 /// A convenience type that can be used in a constant or static.
-pub type BytesConst = Bytes<fn(u8) -> bool>;
+pub type ByteRangesConst = ByteRanges<fn(u8) -> bool>;
 
-/// Searches a string for a set of ASCII characters. Up to 16
-/// characters may be used.
-pub struct AsciiChars<F>(Bytes<F>)
+impl ByteRangesConst {
+    /// Constructs a set of up to 8 ranges, each as a pair of the low
+    /// and high bytes, without a fallback closure.
+    pub const fn from_array<const N: usize>(ranges: [u8; N]) -> Self {
+        assert!(N & 1 == 0, "Ranges need both a low and a high byte");
+        ByteRanges::with_fallback(padded(ranges), N as i32, None)
+    }
+}
+
+/// Searches a string for an ASCII character in a set of inclusive
+/// ranges. Up to 8 ranges may be used.
+pub struct AsciiRanges<F>(ByteRanges<F>)
 where
     F: Fn(u8) -> bool;
 
-impl<F> AsciiChars<F>
+impl<F> AsciiRanges<F>
 where
     F: Fn(u8) -> bool,
 {
-    /// Manual constructor; prefer using [`ascii_chars!`] instead.
+    /// Manual constructor; prefer using [`ascii_ranges!`] instead.
     ///
-    /// Provide an array of ASCII bytes to search for, the number of
-    /// valid bytes provided, and a closure to use when the SIMD
+    /// Provide an array of ranges to search for, each as a pair of
+    /// the low and high ASCII bytes, the number of valid bytes
+    /// provided (two per range), and a closure to use when the SIMD
     /// intrinsics are not available. The closure **must** search for
-    /// the same characters as in the array.
+    /// the same ranges as in the array.
     ///
     /// ### Panics
     ///
     /// - If you provide a non-ASCII byte.
-    pub /* const */ fn new(chars: [u8; 16], len: i32, fallback: F) -> Self {
-        for &b in &chars {
-            assert!(b < 128, "Cannot have non-ASCII bytes");
-        }
-        AsciiChars(Bytes::new(chars, len, fallback))
+    pub const fn new(ranges: [u8; 16], len: i32, fallback: F) -> Self {
+        assert_ascii(&ranges);
+        AsciiRanges(ByteRanges::new(ranges, len, fallback))
     }
 
-    /// Searches the string for the first matching ASCII byte in the set.
+    /// Like [`AsciiRanges::new`], but returns an error instead of
+    /// panicking.
+    pub fn try_new(ranges: [u8; 16], len: i32, fallback: F) -> Result<Self, Error> {
+        error::check_len(len)?;
+        error::check_ascii(&ranges[..len as usize])?;
+        Ok(AsciiRanges(ByteRanges::with_fallback(ranges, len, Some(fallback))))
+    }
+
+    /// Searches the string for the first ASCII character in any of
+    /// the ranges.
     #[inline]
     pub fn find(&self, haystack: &str) -> Option<usize> {
         self.0.find(haystack.as_bytes())
     }
+
+    /// Searches the string for the last ASCII character in any of
+    /// the ranges.
+    #[inline]
+    pub fn rfind(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind(haystack.as_bytes())
+    }
+
+    /// Searches the string for the first character that is *not* in
+    /// any of the ranges.
+    #[inline]
+    pub fn find_not(&self, haystack: &str) -> Option<usize> {
+        self.0.find_not(haystack.as_bytes())
+    }
+
+    /// Searches the string for the last character that is *not* in
+    /// any of the ranges.
+    #[inline]
+    pub fn rfind_not(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind_not(haystack.as_bytes())
+    }
 }
 
 /// A convenience type that can be used in a constant or static.
-pub type AsciiCharsConst = AsciiChars<fn(u8) -> bool>;
+pub type AsciiRangesConst = AsciiRanges<fn(u8) -> bool>;
+
+impl AsciiRangesConst {
+    /// Constructs a set of up to 8 ranges, each as a pair of the low
+    /// and high ASCII bytes, without a fallback closure.
+    ///
+    /// ### Panics
+    ///
+    /// - If you provide a non-ASCII byte.
+    pub const fn from_array<const N: usize>(ranges: [u8; N]) -> Self {
+        assert_ascii(&padded(ranges));
+        AsciiRanges(ByteRanges::from_array(ranges))
+    }
+}
+
+/// Searches a slice for a class of bytes written as a regex-style
+/// bracket expression, such as `[a-z0-9_-]`, `[^"\\]` or
+/// `[[:space:]]`.
+///
+/// The class is searched with the fastest searcher that can
+/// represent it: a [`Bytes`] for up to 16 bytes, a [`NotBytes`] when
+/// all but 16 bytes are in the class, a [`ByteRanges`] for up to 8
+/// ranges, or a [`ByteSet`] otherwise.
+///
+/// ```
+/// use jetscii::{BracketClass, Bytes};
+///
+/// let identifier = Bytes::parse_class("[a-z0-9_-]").unwrap();
+/// assert_eq!(identifier.find(b"(my-name)"), Some(1));
+///
+/// let unescaped: BracketClass = r#"[^"\\]"#.parse().unwrap();
+/// assert_eq!(unescaped.find(br#""\a"#), Some(2));
+/// ```
+///
+/// Besides literal bytes and ranges, a backslash escapes the next
+/// punctuation character, and `\n`, `\r`, `\t`, `\f`, `\v`, `\0`
+/// and `\xHH` name a byte. The POSIX named classes, such as
+/// `[:alpha:]`, match ASCII bytes only.
+pub struct BracketClass {
+    strategy: BracketStrategy,
+}
+
+enum BracketStrategy {
+    Bytes(BytesConst),
+    NotBytes(NotBytesConst),
+    Ranges(ByteRangesConst),
+    Set(ByteSet),
+}
+
+impl BracketClass {
+    fn from_table(table: table::ByteTable) -> Self {
+        let strategy = if table.count() <= 16 {
+            BracketStrategy::Bytes(Bytes::from_slice(&table.bytes()))
+        } else if table.count() >= 256 - 16 {
+            BracketStrategy::NotBytes(NotBytes(Bytes::from_slice(&table.complement().bytes())))
+        } else {
+            let ranges = table.ranges();
+            if ranges.len() <= 8 {
+                let mut array = [0; 16];
+                for (i, &(lo, hi)) in ranges.iter().enumerate() {
+                    array[2 * i] = lo;
+                    array[2 * i + 1] = hi;
+                }
+                BracketStrategy::Ranges(ByteRanges::with_fallback(array, 2 * ranges.len() as i32, None))
+            } else {
+                BracketStrategy::Set(ByteSet::new(&table.bytes()))
+            }
+        };
+
+        BracketClass { strategy }
+    }
+
+    /// Searches the slice for the first byte in the class.
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        match self.strategy {
+            BracketStrategy::Bytes(ref bytes) => bytes.find(haystack),
+            BracketStrategy::NotBytes(ref bytes) => bytes.find(haystack),
+            BracketStrategy::Ranges(ref ranges) => ranges.find(haystack),
+            BracketStrategy::Set(ref set) => set.find(haystack),
+        }
+    }
+
+    /// Searches the slice for the last byte in the class.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        match self.strategy {
+            BracketStrategy::Bytes(ref bytes) => bytes.rfind(haystack),
+            BracketStrategy::NotBytes(ref bytes) => bytes.rfind(haystack),
+            BracketStrategy::Ranges(ref ranges) => ranges.rfind(haystack),
+            BracketStrategy::Set(ref set) => set.rfind(haystack),
+        }
+    }
+
+    /// Returns an iterator over the positions of every byte in the
+    /// class. The iterator can be consumed from either end.
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h [u8]) -> BracketFindIter<'a, 'h> {
+        BracketFindIter {
+            class: self,
+            haystack,
+            start: 0,
+            end: haystack.len(),
+        }
+    }
+}
+
+/// An iterator over the positions of every byte in a bracket
+/// expression.
+///
+/// Created by [`BracketClass::find_iter`].
+pub struct BracketFindIter<'a, 'h> {
+    class: &'a BracketClass,
+    haystack: &'h [u8],
+    // The positions not yet searched are `start..end`.
+    start: usize,
+    end: usize,
+}
+
+impl<'a, 'h> Iterator for BracketFindIter<'a, 'h> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        let found = self.start + self.class.find(&self.haystack[self.start..self.end])?;
+        self.start = found + 1;
+        Some(found)
+    }
+}
+
+impl<'a, 'h> DoubleEndedIterator for BracketFindIter<'a, 'h> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        let found = self.start + self.class.rfind(&self.haystack[self.start..self.end])?;
+        self.end = found;
+        Some(found)
+    }
+}
+
+impl std::str::FromStr for BracketClass {
+    type Err = Error;
+
+    fn from_str(expr: &str) -> Result<Self, Error> {
+        bracket::parse(expr).map(BracketClass::from_table)
+    }
+}
 
 /// Searches a slice for the first occurence of the subslice.
+///
+/// Searches take time linear in the length of the haystack and the
+/// needle, whatever their contents. Without SIMD, needles longer
+/// than 16 bytes skip through the haystack using shift tables.
 pub struct ByteSubstring<'a> {
     // Include this implementation only when compiling for x86_64 as
     // that's the only platform that we support.
     #[cfg(target_arch = "x86_64")]
     simd: simd::ByteSubstring<'a>,
 
-    // If we are *guaranteed* to have SSE 4.2, then there's no reason
-    // to have this implementation.
-    #[cfg(not(target_feature = "sse4.2"))]
+    // Only `find` has a 256-bit implementation so far.
+    #[cfg(target_arch = "x86_64")]
+    avx2: avx2::ByteSubstring<'a>,
+
+    // Every x86_64 machine has SSE2, but if we are *guaranteed* to
+    // have SSE 4.2, then there's no reason to have this
+    // implementation.
+    #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+    sse2: sse2::ByteSubstring<'a>,
+
+    // Every x86_64 machine can use one of the above.
+    #[cfg(all(not(target_arch = "x86_64"), feature = "portable-simd"))]
+    portable: portable::ByteSubstring<'a>,
+
+    #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
     fallback: fallback::ByteSubstring<'a>,
+
+    // Without SIMD to find the anchor byte, long needles are searched
+    // with shift tables instead.
+    #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+    long: Option<horspool::ByteSubstring<'a>>,
 }
 
 impl<'a> ByteSubstring<'a> {
-    pub /* const */ fn new(needle: &'a [u8]) -> Self {
+    pub const fn new(needle: &'a [u8]) -> Self {
         ByteSubstring {
             #[cfg(target_arch = "x86_64")]
             simd: simd::ByteSubstring::new(needle),
 
-            #[cfg(not(target_feature = "sse4.2"))]
+            #[cfg(target_arch = "x86_64")]
+            avx2: avx2::ByteSubstring::new(needle),
+
+            #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+            sse2: sse2::ByteSubstring::new(needle),
+
+            #[cfg(all(not(target_arch = "x86_64"), feature = "portable-simd"))]
+            portable: portable::ByteSubstring::new(needle),
+
+            #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
             fallback: fallback::ByteSubstring::new(needle),
+
+            #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+            long: if needle.len() >= horspool::LONG_NEEDLE_MIN_LEN {
+                Some(horspool::ByteSubstring::new(needle))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Like [`ByteSubstring::new`], but rejects an empty needle,
+    /// which would match at every position.
+    ///
+    /// ```
+    /// use jetscii::{ByteSubstring, Error};
+    ///
+    /// assert_eq!(ByteSubstring::try_new(b"").err(), Some(Error::EmptyNeedle));
+    /// ```
+    pub fn try_new(needle: &'a [u8]) -> Result<Self, Error> {
+        if needle.is_empty() {
+            return Err(Error::EmptyNeedle);
         }
+        Ok(ByteSubstring::new(needle))
     }
 
     #[cfg(feature = "pattern")]
     fn needle_len(&self) -> usize {
         dispatch! {
             simd: self.simd.needle_len(),
+            sse2: self.sse2.needle_len(),
+            portable: self.portable.needle_len(),
             fallback: self.fallback.needle_len(),
         }
     }
@@ -321,11 +1924,39 @@ impl<'a> ByteSubstring<'a> {
     /// Searches the slice for the first occurence of the subslice.
     #[inline]
     pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+        {
+            if let Some(ref long) = self.long {
+                return long.find(haystack);
+            }
+        }
+
         dispatch! {
+            avx2: unsafe { self.avx2.find(haystack) },
             simd: unsafe { self.simd.find(haystack) },
+            sse2: unsafe { self.sse2.find(haystack) },
+            portable: self.portable.find(haystack),
             fallback: self.fallback.find(haystack),
         }
     }
+
+    /// Searches the slice for the last occurence of the subslice.
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(all(not(target_arch = "x86_64"), not(feature = "portable-simd")))]
+        {
+            if let Some(ref long) = self.long {
+                return long.rfind(haystack);
+            }
+        }
+
+        dispatch! {
+            simd: unsafe { self.simd.rfind(haystack) },
+            sse2: unsafe { self.sse2.rfind(haystack) },
+            portable: self.portable.rfind(haystack),
+            fallback: self.fallback.rfind(haystack),
+        }
+    }
 }
 
 /// A convenience type that can be used in a constant or static.
@@ -335,10 +1966,16 @@ pub type ByteSubstringConst = ByteSubstring<'static>;
 pub struct Substring<'a>(ByteSubstring<'a>);
 
 impl<'a> Substring<'a> {
-    pub /* const */ fn new(needle: &'a str) -> Self {
+    pub const fn new(needle: &'a str) -> Self {
         Substring(ByteSubstring::new(needle.as_bytes()))
     }
 
+    /// Like [`Substring::new`], but rejects an empty needle, which
+    /// would match at every position.
+    pub fn try_new(needle: &'a str) -> Result<Self, Error> {
+        ByteSubstring::try_new(needle.as_bytes()).map(Substring)
+    }
+
     #[cfg(feature = "pattern")]
     fn needle_len(&self) -> usize {
         self.0.needle_len()
@@ -349,22 +1986,134 @@ impl<'a> Substring<'a> {
     pub fn find(&self, haystack: &str) -> Option<usize> {
         self.0.find(haystack.as_bytes())
     }
+
+    /// Searches the string for the last occurence of the substring.
+    #[inline]
+    pub fn rfind(&self, haystack: &str) -> Option<usize> {
+        self.0.rfind(haystack.as_bytes())
+    }
 }
 
 /// A convenience type that can be used in a constant or static.
 pub type SubstringConst = Substring<'static>;
 
+#[cfg(test)]
+mod test {
+    use proptest::{collection::vec as vec_strat, prelude::*};
+
+    use super::*;
+
+    fn needle() -> impl Strategy<Value = Vec<u8>> {
+        vec_strat(any::<u8>(), 1..=16)
+    }
+
+    #[test]
+    fn try_new_checks_only_the_valid_bytes() {
+        let mut chars = [0xFF; 16];
+        chars[..2].copy_from_slice(b"az");
+
+        let ascii = AsciiChars::try_new(chars, 2, |b| b == b'a' || b == b'z').unwrap();
+        assert_eq!(ascii.find("xyz"), Some(2));
+        assert!(NotAsciiChars::try_new(chars, 2, |b| b == b'a' || b == b'z').is_ok());
+
+        let ranges = AsciiRanges::try_new(chars, 2, |b| b.is_ascii_lowercase()).unwrap();
+        assert_eq!(ranges.find("ABc"), Some(2));
+
+        assert_eq!(
+            AsciiChars::try_new(chars, 3, |_| false).err(),
+            Some(Error::NonAscii { byte: 0xFF, index: 2 })
+        );
+        assert_eq!(
+            AsciiRanges::try_new(chars, 17, |_| false).err(),
+            Some(Error::TooManyBytes { len: 17 })
+        );
+        assert_eq!(
+            NotBytes::try_new(chars, -1, |_| false).err(),
+            Some(Error::NegativeLength { len: -1 })
+        );
+    }
+
+    #[test]
+    fn fallback_closures_are_dropped() {
+        use std::rc::Rc;
+
+        let owned = Rc::new(());
+
+        let captured = Rc::clone(&owned);
+        drop(Bytes::new([b'a'; 16], 1, move |b| {
+            let _ = &captured;
+            b == b'a'
+        }));
+        assert_eq!(Rc::strong_count(&owned), 1);
+
+        let captured = Rc::clone(&owned);
+        drop(ByteRanges::new([b'a', b'z', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2, move |b| {
+            let _ = &captured;
+            b.is_ascii_lowercase()
+        }));
+        assert_eq!(Rc::strong_count(&owned), 1);
+    }
+
+    proptest! {
+        #[test]
+        fn set_operations_search_for_the_combined_bytes(
+            a in needle(),
+            b in needle(),
+            haystack in any::<Vec<u8>>(),
+        ) {
+            let (ba, bb) = (Bytes::from_slice(&a), Bytes::from_slice(&b));
+            let position = |f: &dyn Fn(&u8) -> bool| haystack.iter().position(f);
+
+            let in_union = (0..=255).filter(|x| a.contains(x) || b.contains(x)).count();
+            match ba.union(&bb) {
+                Ok(union) => {
+                    assert_eq!(union.find(&haystack), position(&|x| a.contains(x) || b.contains(x)))
+                }
+                Err(e) => assert_eq!(e, Error::TooManyBytes { len: in_union }),
+            }
+
+            let intersection = ba.intersection(&bb);
+            assert_eq!(intersection.find(&haystack), position(&|x| a.contains(x) && b.contains(x)));
+
+            let difference = ba.difference(&bb);
+            assert_eq!(difference.find(&haystack), position(&|x| a.contains(x) && !b.contains(x)));
+
+            let complement = ba.complement();
+            assert_eq!(complement.find(&haystack), position(&|x| !a.contains(x)));
+            assert_eq!(complement.rfind(&haystack), haystack.iter().rposition(|x| !a.contains(x)));
+        }
+
+        #[test]
+        fn complements_contain_every_other_byte(needle in needle()) {
+            let bytes = Bytes::from_slice(&needle);
+            let complement = bytes.complement();
+
+            for b in 0..=255 {
+                assert_eq!(complement.contains(b), !needle.contains(&b));
+            }
+            let expected: Vec<u8> = (0..=255).filter(|b| !needle.contains(b)).collect();
+            assert_eq!(complement.iter().collect::<Vec<_>>(), expected);
+
+            let ascii: Vec<u8> = needle.iter().map(|b| b & 0x7F).collect();
+            let chars = AsciiChars::try_from_str(std::str::from_utf8(&ascii).unwrap()).unwrap();
+            let complement = chars.complement();
+
+            let expected: Vec<u8> = (0..=255).filter(|b| !ascii.contains(b)).collect();
+            assert_eq!(complement.iter().collect::<Vec<_>>(), expected);
+            assert!(complement.contains(0x80));
+        }
+    }
+}
+
 #[cfg(all(test, feature = "benchmarks"))]
 mod bench {
     extern crate test;
 
     use super::*;
 
-    lazy_static! {
-        static ref SPACE: AsciiCharsConst = ascii_chars!(' ');
-        static ref XML_DELIM_3: AsciiCharsConst = ascii_chars!('<', '>', '&');
-        static ref XML_DELIM_5: AsciiCharsConst = ascii_chars!('<', '>', '&', '\'', '"');
-    }
+    static SPACE: AsciiCharsConst = ascii_chars!(' ');
+    static XML_DELIM_3: AsciiCharsConst = ascii_chars!('<', '>', '&');
+    static XML_DELIM_5: AsciiCharsConst = ascii_chars!('<', '>', '&', '\'', '"');
 
     fn prefix_string() -> String {
         "a".repeat(5 * 1024 * 1024)
@@ -483,9 +2232,7 @@ mod bench {
         })
     }
 
-    lazy_static! {
-        static ref XYZZY: Substring<'static> = Substring::new("xyzzy");
-    }
+    static XYZZY: SubstringConst = Substring::new("xyzzy");
 
     fn bench_substring<F>(b: &mut test::Bencher, f: F)
     where
@@ -507,4 +2254,145 @@ mod bench {
     fn substring_stdlib_find(b: &mut test::Bencher) {
         bench_substring(b, |hs| hs.find("xyzzy"))
     }
+
+    // Repetitive markup, so that most of the needle matches in many
+    // places.
+    static LONG_NEEDLE: &str = "<a></a><a></a><a></a><a></a>!";
+
+    fn bench_long_substring<F>(b: &mut test::Bencher, f: F)
+    where
+        F: Fn(&[u8]) -> Option<usize>,
+    {
+        let mut haystack = "<a></a>".repeat(5 * 1024 * 1024 / 7);
+        haystack.push_str(LONG_NEEDLE);
+
+        b.iter(|| test::black_box(f(haystack.as_bytes())));
+        b.bytes = haystack.len() as u64;
+    }
+
+    fn bench_long_substring_prose<F>(b: &mut test::Bencher, f: F)
+    where
+        F: Fn(&[u8]) -> Option<usize>,
+    {
+        let mut haystack = "the quick brown fox jumps over the lazy dog <b>".repeat(5 * 1024 * 1024 / 47);
+        haystack.push_str(LONG_NEEDLE);
+
+        b.iter(|| test::black_box(f(haystack.as_bytes())));
+        b.bytes = haystack.len() as u64;
+    }
+
+    // The SIMD implementations below are only run on machines that
+    // support them; calling them anywhere else is undefined behavior.
+
+    #[bench]
+    fn long_substring_horspool(b: &mut test::Bencher) {
+        let substring = horspool::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring(b, |hs| substring.find(hs))
+    }
+
+    #[bench]
+    #[cfg(target_arch = "x86_64")]
+    fn long_substring_sse42(b: &mut test::Bencher) {
+        if !is_x86_feature_detected!("sse4.2") {
+            return;
+        }
+
+        let substring = simd::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    #[bench]
+    #[cfg(target_arch = "x86_64")]
+    fn long_substring_avx2(b: &mut test::Bencher) {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let substring = avx2::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    #[bench]
+    #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+    fn long_substring_sse2(b: &mut test::Bencher) {
+        let substring = sse2::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    #[bench]
+    #[cfg(all(target_arch = "x86_64", not(target_feature = "sse4.2")))]
+    fn long_substring_prose_sse2(b: &mut test::Bencher) {
+        let substring = sse2::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring_prose(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    #[bench]
+    fn long_substring_prose_horspool(b: &mut test::Bencher) {
+        let substring = horspool::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring_prose(b, |hs| substring.find(hs))
+    }
+
+    #[bench]
+    #[cfg(target_arch = "x86_64")]
+    fn long_substring_prose_sse42(b: &mut test::Bencher) {
+        if !is_x86_feature_detected!("sse4.2") {
+            return;
+        }
+
+        let substring = simd::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring_prose(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    #[bench]
+    #[cfg(target_arch = "x86_64")]
+    fn long_substring_prose_avx2(b: &mut test::Bencher) {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let substring = avx2::ByteSubstring::new(LONG_NEEDLE.as_bytes());
+        bench_long_substring_prose(b, |hs| unsafe { substring.find(hs) })
+    }
+
+    fn bench_short_xml_delim_5<F>(b: &mut test::Bencher, f: F)
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        // Tokens of 1 to 15 bytes, starting at every alignment
+        let haystack = "a".repeat(16 * 1024);
+        let tokens: Vec<_> = (0..1024).map(|i| &haystack[i * 13..][..i % 15 + 1]).collect();
+
+        b.iter(|| {
+            for token in &tokens {
+                test::black_box(f(token));
+            }
+        });
+        b.bytes = tokens.iter().map(|t| t.len() as u64).sum();
+    }
+
+    #[bench]
+    fn short_xml_delim_5_ascii_chars(b: &mut test::Bencher) {
+        bench_short_xml_delim_5(b, |hs| XML_DELIM_5.find(hs))
+    }
+
+    #[bench]
+    fn short_xml_delim_5_ascii_chars_const(b: &mut test::Bencher) {
+        // Each use of a `const` is a new searcher.
+        const XML_DELIM_5_CONST: AsciiCharsConst = ascii_chars!('<', '>', '&', '\'', '"');
+        bench_short_xml_delim_5(b, |hs| XML_DELIM_5_CONST.find(hs))
+    }
+
+    #[bench]
+    fn short_xml_delim_5_stdlib_find_char_set(b: &mut test::Bencher) {
+        bench_short_xml_delim_5(b, |hs| hs.find(&['<', '>', '&', '\'', '"'][..]))
+    }
+
+    #[bench]
+    fn short_xml_delim_5_stdlib_iterator_position(b: &mut test::Bencher) {
+        bench_short_xml_delim_5(b, |hs| {
+            hs.as_bytes()
+                .iter()
+                .position(|&c| c == b'<' || c == b'>' || c == b'&' || c == b'\'' || c == b'"')
+        })
+    }
 }
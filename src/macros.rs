@@ -0,0 +1,356 @@
+//! Support code for the `bytes!`, `ascii_chars!`, `byte_ranges!`
+//! and `ascii_ranges!` macros. When every argument is a literal, the
+//! macros evaluate them in a `const` item so that every problem with
+//! the set is reported at compile time.
+
+use std::ops::RangeInclusive;
+
+use {
+    AsciiChars, AsciiCharsConst, AsciiRanges, AsciiRangesConst, ByteRanges, ByteRangesConst, Bytes, BytesConst,
+    NotAsciiChars, NotAsciiCharsConst, NotBytes, NotBytesConst,
+};
+
+const MAX_LEN: usize = 16;
+
+/// The bytes collected so far.
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone)]
+pub struct Set {
+    bytes: [u8; MAX_LEN],
+    len: usize,
+    ascii: bool,
+    duplicate: bool,
+}
+
+impl Set {
+    /// A set for `bytes!`, which accepts any byte.
+    pub const fn bytes() -> Self {
+        Set {
+            bytes: [0; MAX_LEN],
+            len: 0,
+            ascii: false,
+            duplicate: false,
+        }
+    }
+
+    /// A set for `ascii_chars!`, which only accepts ASCII bytes.
+    pub const fn ascii() -> Self {
+        Set {
+            bytes: [0; MAX_LEN],
+            len: 0,
+            ascii: true,
+            duplicate: false,
+        }
+    }
+
+    pub const fn checked(self) -> Self {
+        assert!(self.len > 0, "Must have at least one byte");
+        self
+    }
+
+    /// Repeating a literal is most likely a typo, so the macros only
+    /// allow duplicates in other arguments.
+    pub const fn without_duplicates(self) -> Self {
+        assert!(!self.duplicate, "Cannot have duplicate bytes");
+        self
+    }
+
+    pub const fn into_bytes(self) -> BytesConst {
+        Bytes::with_fallback(self.bytes, self.len as i32, None)
+    }
+
+    pub const fn into_not_bytes(self) -> NotBytesConst {
+        NotBytes(self.into_bytes())
+    }
+
+    pub const fn into_ascii_chars(self) -> AsciiCharsConst {
+        AsciiChars(self.into_bytes())
+    }
+
+    pub const fn into_not_ascii_chars(self) -> NotAsciiCharsConst {
+        NotAsciiChars(self.into_ascii_chars())
+    }
+
+    const fn push(mut self, byte: u8) -> Self {
+        assert!(!self.ascii || byte < 128, "Cannot have non-ASCII bytes");
+
+        let mut i = 0;
+        while i < self.len {
+            if self.bytes[i] == byte {
+                self.duplicate = true;
+                return self;
+            }
+            i += 1;
+        }
+
+        assert!(self.len < MAX_LEN, "Cannot have more than 16 bytes");
+        self.bytes[self.len] = byte;
+        self.len += 1;
+        self
+    }
+
+    const fn push_all(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self = self.push(bytes[i]);
+            i += 1;
+        }
+        self
+    }
+
+    const fn push_range(mut self, start: u8, end: u8) -> Self {
+        assert!(start <= end, "Cannot have an empty range");
+
+        let mut byte = start;
+        loop {
+            self = self.push(byte);
+            if byte == end {
+                break self;
+            }
+            byte += 1;
+        }
+    }
+}
+
+/// The ranges collected so far, as pairs of the low and high bytes.
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone)]
+pub struct Ranges {
+    bytes: [u8; MAX_LEN],
+    len: usize,
+    ascii: bool,
+}
+
+impl Ranges {
+    /// Ranges for `byte_ranges!`, which accepts any byte.
+    pub const fn bytes() -> Self {
+        Ranges {
+            bytes: [0; MAX_LEN],
+            len: 0,
+            ascii: false,
+        }
+    }
+
+    /// Ranges for `ascii_ranges!`, which only accepts ASCII bytes.
+    pub const fn ascii() -> Self {
+        Ranges {
+            bytes: [0; MAX_LEN],
+            len: 0,
+            ascii: true,
+        }
+    }
+
+    pub const fn into_byte_ranges(self) -> ByteRangesConst {
+        ByteRanges::with_fallback(self.bytes, self.len as i32, None)
+    }
+
+    pub const fn into_ascii_ranges(self) -> AsciiRangesConst {
+        AsciiRanges(self.into_byte_ranges())
+    }
+
+    const fn push(mut self, start: u8, end: u8) -> Self {
+        assert!(start <= end, "Cannot have an empty range");
+        assert!(!self.ascii || end < 128, "Cannot have non-ASCII bytes");
+        assert!(self.len < MAX_LEN, "Cannot have more than 8 ranges");
+
+        self.bytes[self.len] = start;
+        self.bytes[self.len + 1] = end;
+        self.len += 2;
+        self
+    }
+}
+
+const fn ascii_char(c: char) -> u8 {
+    assert!(c.is_ascii(), "Cannot have non-ASCII characters");
+    c as u8
+}
+
+/// One macro argument. Each supported argument type has its own
+/// inherent `add_to`, as trait methods cannot be called in a
+/// `const` context.
+#[doc(hidden)]
+pub struct Arg<T>(pub T);
+
+impl Arg<u8> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push(self.0)
+    }
+}
+
+impl Arg<char> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push(ascii_char(self.0))
+    }
+}
+
+impl Arg<&str> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push_all(self.0.as_bytes())
+    }
+}
+
+impl Arg<&[u8]> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push_all(self.0)
+    }
+}
+
+impl<const N: usize> Arg<&[u8; N]> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push_all(self.0)
+    }
+}
+
+impl Arg<RangeInclusive<u8>> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push_range(*self.0.start(), *self.0.end())
+    }
+
+    pub const fn add_to_ranges(self, ranges: Ranges) -> Ranges {
+        ranges.push(*self.0.start(), *self.0.end())
+    }
+}
+
+impl Arg<RangeInclusive<char>> {
+    pub const fn add_to(self, set: Set) -> Set {
+        set.push_range(ascii_char(*self.0.start()), ascii_char(*self.0.end()))
+    }
+
+    pub const fn add_to_ranges(self, ranges: Ranges) -> Ranges {
+        ranges.push(ascii_char(*self.0.start()), ascii_char(*self.0.end()))
+    }
+}
+
+/// Collects the macro arguments into a `Set`. Literals are checked
+/// at compile time; any other argument is checked when the
+/// expression is evaluated, which is also at compile time in a
+/// `const` or `static`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __jetscii_set {
+    (@add $set:expr; $($arg:expr),+) => {{
+        let set = $set;
+        $(let set = $crate::macros::Arg($arg).add_to(set);)+
+        set.checked()
+    }};
+    ($set:expr; $($lo:literal $(..= $hi:literal)?),+ $(,)?) => {{
+        const SET: $crate::macros::Set =
+            $crate::__jetscii_set!(@add $set; $($lo $(..= $hi)?),+).without_duplicates();
+        SET
+    }};
+    ($set:expr; $($arg:expr),+ $(,)?) => (
+        $crate::__jetscii_set!(@add $set; $($arg),+)
+    );
+}
+
+/// Collects the macro arguments into `Ranges`, checking literals at
+/// compile time like `__jetscii_set!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __jetscii_ranges {
+    (@add $ranges:expr; $($arg:expr),+) => {{
+        let ranges = $ranges;
+        $(let ranges = $crate::macros::Arg($arg).add_to_ranges(ranges);)+
+        ranges
+    }};
+    ($ranges:expr; $($lo:literal ..= $hi:literal),+ $(,)?) => {{
+        const RANGES: $crate::macros::Ranges = $crate::__jetscii_ranges!(@add $ranges; $($lo ..= $hi),+);
+        RANGES
+    }};
+    ($ranges:expr; $($arg:expr),+ $(,)?) => (
+        $crate::__jetscii_ranges!(@add $ranges; $($arg),+)
+    );
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn string_literals_add_each_byte() {
+        let chars = ascii_chars!("<>&\"'");
+        assert_eq!(chars.find("a'b"), Some(1));
+        assert_eq!(chars.find("a\"b"), Some(1));
+        assert_eq!(chars.find("abc"), None);
+
+        let bytes = bytes!(b"\r\n");
+        assert_eq!(bytes.find(b"ab\rc"), Some(2));
+        assert_eq!(bytes.find(b"abc\n"), Some(3));
+    }
+
+    #[test]
+    fn ranges_add_each_byte_inclusively() {
+        let hex = ascii_chars!('a'..='f', '0'..='9');
+        assert_eq!(hex.find("xyz9"), Some(3));
+        assert_eq!(hex.find("xyzf"), Some(3));
+        assert_eq!(hex.find("xyzg"), None);
+
+        let bytes = bytes!(0xF0..=0xFF);
+        assert_eq!(bytes.find(&[0x00, 0xEF, 0xFF]), Some(2));
+    }
+
+    #[test]
+    fn arguments_can_be_mixed() {
+        let chars = ascii_chars!(^ ' ', "\t\r", b'\n', 'a'..='c');
+        assert_eq!(chars.find(" \t\r\nabcd"), Some(7));
+    }
+
+    #[test]
+    fn arguments_can_be_variables() {
+        let (quote, range, string) = ('"', b'0'..=b'9', "<>");
+        let chars = ascii_chars!(quote, range, string, '&');
+        assert_eq!(chars.find("a&b"), Some(1));
+        assert_eq!(chars.find("a7b"), Some(1));
+        assert_eq!(chars.find("a\"b"), Some(1));
+        assert_eq!(chars.find("a>b"), Some(1));
+    }
+
+    #[test]
+    fn duplicate_variables_are_ignored() {
+        let (a, b) = (b'a', b'a');
+        let bytes = bytes!(a, b, b'a'..=b'b');
+        assert_eq!(bytes.iter().collect::<Vec<_>>(), b"ab");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot have non-ASCII characters")]
+    fn variables_are_checked_when_evaluated() {
+        let c = 'é';
+        ascii_chars!(c);
+    }
+
+    #[test]
+    fn ranges_keep_their_bounds() {
+        let ranges = byte_ranges!(0x80..=0xFF, 0x00..=0x08);
+        assert_eq!(ranges.find(&[0x7F, 0x09, 0xC8]), Some(2));
+        assert_eq!(ranges.find(&[0x7F, 0x09, 0x05]), Some(2));
+
+        let ranges = ascii_ranges!('a'..='z', b'0'..=b'9');
+        assert_eq!(ranges.find("AB7"), Some(2));
+        assert_eq!(ranges.find("é"), None);
+    }
+
+    #[test]
+    fn range_variables_are_checked_when_evaluated() {
+        let digits = '0'..='9';
+        assert_eq!(ascii_ranges!(digits).find("x1"), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot have an empty range")]
+    fn reversed_range_variables_panic() {
+        let (hi, lo) = (b'z', b'a');
+        byte_ranges!(hi..=lo);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot have non-ASCII characters")]
+    fn non_ascii_range_variables_panic() {
+        let latin = '\u{100}'..='\u{17F}';
+        ascii_ranges!(latin);
+    }
+
+    #[test]
+    fn sixteen_bytes_are_allowed() {
+        let bytes = bytes!(0x00..=0x0F);
+        assert_eq!(bytes.find(&[0x10, 0x0F]), Some(1));
+    }
+}
@@ -0,0 +1,218 @@
+// On x86_64, the `simd`, `sse2` and `avx2` modules are used instead
+// and this is only compared against them in tests.
+#![cfg_attr(target_arch = "x86_64", allow(dead_code))]
+
+use std::simd::{cmp::SimdPartialEq, u8x16, Mask};
+
+use table::ByteList;
+use twoway::TwoWay;
+
+const LANES: usize = 16;
+
+/// Compares each chunk against every byte in the set, one splatted
+/// byte at a time, and combines the results.
+pub struct Bytes<'s> {
+    set: &'s ByteList,
+}
+
+impl<'s> Bytes<'s> {
+    pub const fn new(set: &'s ByteList) -> Self {
+        Bytes { set }
+    }
+
+    /// Compares an entire chunk, returning one bit per byte.
+    #[inline]
+    fn mask(&self, chunk: u8x16) -> u16 {
+        self.set
+            .as_slice()
+            .iter()
+            .fold(Mask::splat(false), |matched, &b| matched | chunk.simd_eq(u8x16::splat(b)))
+            .to_bitmask() as u16
+    }
+
+    /// Compares a chunk of fewer than `LANES` bytes, returning one
+    /// bit per byte.
+    #[inline]
+    fn partial_mask<M>(&self, part: &[u8], mask: &M) -> u16
+    where
+        M: Fn(u16) -> u16,
+    {
+        let valid = (1_u32 << part.len()) - 1;
+        mask(self.mask(u8x16::load_or_default(part))) & valid as u16
+    }
+
+    /// Only the aligned middle of the haystack is read as whole
+    /// vectors, so there is no risk of reading past its end.
+    #[inline]
+    fn find_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u16) -> u16,
+    {
+        let (prefix, middle, suffix) = haystack.as_simd::<LANES>();
+
+        let found = self.partial_mask(prefix, &mask);
+        if found != 0 {
+            return Some(found.trailing_zeros() as usize);
+        }
+
+        for (i, &chunk) in middle.iter().enumerate() {
+            let found = mask(self.mask(chunk));
+            if found != 0 {
+                return Some(prefix.len() + i * LANES + found.trailing_zeros() as usize);
+            }
+        }
+
+        let found = self.partial_mask(suffix, &mask);
+        if found != 0 {
+            let offset = haystack.len() - suffix.len();
+            return Some(offset + found.trailing_zeros() as usize);
+        }
+
+        None
+    }
+
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| mask)
+    }
+
+    #[inline]
+    pub fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| !mask)
+    }
+
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| mask)
+    }
+
+    #[inline]
+    pub fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| !mask)
+    }
+
+    #[inline]
+    fn rfind_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u16) -> u16,
+    {
+        let (prefix, middle, suffix) = haystack.as_simd::<LANES>();
+        let last_match = |found: u16| 15 - found.leading_zeros() as usize;
+
+        let found = self.partial_mask(suffix, &mask);
+        if found != 0 {
+            let offset = haystack.len() - suffix.len();
+            return Some(offset + last_match(found));
+        }
+
+        for (i, &chunk) in middle.iter().enumerate().rev() {
+            let found = mask(self.mask(chunk));
+            if found != 0 {
+                return Some(prefix.len() + i * LANES + last_match(found));
+            }
+        }
+
+        let found = self.partial_mask(prefix, &mask);
+        if found != 0 {
+            return Some(last_match(found));
+        }
+
+        None
+    }
+}
+
+/// Uses the rarest byte of the needle to find candidates, then checks
+/// each candidate with the Two-Way algorithm.
+pub struct ByteSubstring<'a> {
+    two_way: TwoWay<'a>,
+    anchor: ByteList,
+}
+
+impl<'a> ByteSubstring<'a> {
+    pub const fn new(needle: &'a [u8]) -> Self {
+        let two_way = TwoWay::new(needle);
+
+        let mut bytes = [0; 16];
+        let len = if let Some(anchor) = two_way.anchor_byte() {
+            bytes[0] = anchor;
+            1
+        } else {
+            0
+        };
+
+        ByteSubstring {
+            two_way,
+            anchor: ByteList::new(bytes, len),
+        }
+    }
+
+    #[cfg(feature = "pattern")]
+    pub fn needle_len(&self) -> usize {
+        self.two_way.needle().len()
+    }
+
+    #[inline]
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.find(haystack, |candidates| Bytes::new(&self.anchor).find(candidates))
+    }
+
+    #[inline]
+    pub fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.rfind(haystack, |candidates| Bytes::new(&self.anchor).rfind(candidates))
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use simd;
+
+    /// Creates a set of bytes and an offset inside them.
+    fn haystack() -> BoxedStrategy<(Vec<u8>, usize)> {
+        any::<Vec<u8>>()
+            .prop_flat_map(|data| {
+                let len = 0..=data.len();
+                (Just(data), len)
+            })
+            .boxed()
+    }
+
+    /// Creates an array and the number of valid values
+    fn needle() -> BoxedStrategy<([u8; 16], usize)> {
+        (any::<[u8; 16]>(), 0..=16_usize).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_simd_does(
+            ((needle, len), (haystack, start)) in (needle(), haystack())
+        ) {
+            let haystack = &haystack[start..];
+            let set = ByteList::new(needle, len);
+            let portable = Bytes::new(&set);
+            let simd = simd::Bytes::new(needle, len as i32);
+
+            unsafe {
+                assert_eq!(simd.find(haystack), portable.find(haystack));
+                assert_eq!(simd.rfind(haystack), portable.rfind(haystack));
+                assert_eq!(simd.find_not(haystack), portable.find_not(haystack));
+                assert_eq!(simd.rfind_not(haystack), portable.rfind_not(haystack));
+            }
+        }
+
+        #[test]
+        fn works_as_simd_does_for_byte_substrings(
+            (needle, haystack) in (any::<Vec<u8>>(), any::<Vec<u8>>())
+        ) {
+            let portable = ByteSubstring::new(&needle);
+            let simd = simd::ByteSubstring::new(&needle);
+
+            unsafe {
+                assert_eq!(simd.find(&haystack), portable.find(&haystack));
+                assert_eq!(simd.rfind(&haystack), portable.rfind(&haystack));
+            }
+        }
+    }
+}
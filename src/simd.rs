@@ -5,15 +5,31 @@
 use std::{
     arch::x86_64::{
         __m128i, _mm_cmpestri, _mm_cmpestrm, _mm_extract_epi16, _mm_loadu_si128, _SIDD_CMP_EQUAL_ORDERED,
+        _SIDD_CMP_RANGES, _SIDD_MASKED_NEGATIVE_POLARITY,
     },
     cmp::min,
     slice,
 };
 
-include!(concat!(env!("OUT_DIR"), "/src/simd_macros.rs"));
+use twoway::TwoWay;
+
+#[cfg(test)]
+macro_rules! simd_bytes {
+    ($($b:expr),+) => {{
+        let bytes = [$($b),+];
+        $crate::simd::Bytes::new($crate::padded(bytes), bytes.len() as i32)
+    }};
+}
 
 const BYTES_PER_OPERATION: usize = 16;
 
+/// The number of bytes compared before `find` checks for a match.
+const BYTES_PER_BLOCK: usize = 4 * BYTES_PER_OPERATION;
+
+/// Pages are never smaller than this on x86_64, so a read that stays
+/// within one of these blocks can't cross into a protected page.
+const MIN_PAGE_SIZE: usize = 4096;
+
 union TransmuteToSimd {
     simd: __m128i,
     bytes: [u8; 16],
@@ -45,6 +61,13 @@ where
         return None;
     }
 
+    // Short haystacks can usually be compared with a single
+    // unaligned read, skipping the misaligned prefix below, which
+    // otherwise dominates the cost of searching short tokens.
+    if haystack.len() < BYTES_PER_OPERATION && read_stays_in_page(haystack.as_ptr()) {
+        return packed.cmpestri(haystack.as_ptr(), haystack.len() as i32);
+    }
+
     let mut offset = 0;
 
     if let Some(misaligned) = Misalignment::new(haystack) {
@@ -61,20 +84,29 @@ where
         offset += misaligned.bytes_until_alignment;
     }
 
-    // TODO: try removing the 16-byte loop and check the disasm
-    let n_complete_chunks = haystack.len() / BYTES_PER_OPERATION;
-
     // Getting the pointer once before the loop avoids the
     // overhead of manipulating the length of the slice inside the
     // loop.
     let mut haystack_ptr = haystack.as_ptr();
     let mut chunk_offset = 0;
+
+    if haystack.len() >= BYTES_PER_BLOCK {
+        let n_block_bytes = haystack.len() / BYTES_PER_BLOCK * BYTES_PER_BLOCK;
+        if let Some(location) = find_in_blocks(&packed, &haystack[..n_block_bytes]) {
+            return Some(offset + location);
+        }
+
+        haystack_ptr = haystack_ptr.add(n_block_bytes);
+        chunk_offset += n_block_bytes;
+    }
+
+    let n_complete_chunks = (haystack.len() - chunk_offset) / BYTES_PER_OPERATION;
     for _ in 0..n_complete_chunks {
         if let Some(location) = packed.cmpestri(haystack_ptr, BYTES_PER_OPERATION as i32) {
             return Some(offset + chunk_offset + location);
         }
 
-        haystack_ptr = haystack_ptr.offset(BYTES_PER_OPERATION as isize);
+        haystack_ptr = haystack_ptr.add(BYTES_PER_OPERATION);
         chunk_offset += BYTES_PER_OPERATION;
     }
     haystack = &haystack[chunk_offset..];
@@ -87,12 +119,85 @@ where
 
     // By this point, the haystack's length must be less than 16
     // bytes. It is thus reasonable to truncate it into an i32.
-    debug_assert!(haystack.len() < ::std::i32::MAX as usize);
+    debug_assert!(haystack.len() < i32::MAX as usize);
     packed
         .cmpestri(haystack.as_ptr(), haystack.len() as i32)
         .map(|loc| offset + loc)
 }
 
+/// Matches are usually rare, so this compares four chunks before
+/// branching once on all of them. The haystack must be aligned and
+/// hold a whole number of 64-byte blocks.
+///
+/// This is kept out of line so that [`find`] stays small enough to
+/// inline, which matters most for short haystacks.
+#[inline(never)]
+#[target_feature(enable = "sse4.2")]
+unsafe fn find_in_blocks<C, const CONTROL_BYTE: i32>(
+    packed: &PackedCompare<C, CONTROL_BYTE>,
+    haystack: &[u8],
+) -> Option<usize>
+where
+    C: PackedCompareControl,
+{
+    debug_assert_eq!(0, haystack.as_ptr() as usize % BYTES_PER_OPERATION);
+    debug_assert_eq!(0, haystack.len() % BYTES_PER_BLOCK);
+
+    let mut block_ptr = haystack.as_ptr();
+    for block_offset in (0..haystack.len()).step_by(BYTES_PER_BLOCK) {
+        if let Some(location) = packed.find_in_block(block_ptr) {
+            return Some(block_offset + location);
+        }
+        block_ptr = block_ptr.add(BYTES_PER_BLOCK);
+    }
+
+    None
+}
+
+/// Checks that a 16-byte read starting at `ptr` stays within one
+/// page, even if it goes past the end of the haystack.
+#[inline]
+fn read_stays_in_page(ptr: *const u8) -> bool {
+    ptr as usize % MIN_PAGE_SIZE <= MIN_PAGE_SIZE - BYTES_PER_OPERATION
+}
+
+/// Searching from the end reads only 16-byte aligned chunks, so no
+/// read can cross into another page, just like the leading chunk of
+/// [`find`]. The first and last chunks may hold bytes outside of the
+/// haystack; their bits are cleared from the mask before we look for
+/// the last match.
+#[inline]
+#[target_feature(enable = "sse4.2")]
+unsafe fn rfind<C, const CONTROL_BYTE: i32>(packed: PackedCompare<C, CONTROL_BYTE>, haystack: &[u8]) -> Option<usize>
+where
+    C: PackedCompareControl,
+{
+    if haystack.is_empty() {
+        return None;
+    }
+
+    let start = haystack.as_ptr() as usize;
+    let end = start + haystack.len();
+    let first_chunk = start & !0xF;
+    let mut chunk = (end - 1) & !0xF;
+
+    loop {
+        let mask = packed.mask_within(chunk, start, end);
+
+        if mask != 0 {
+            // The last 1, starting from Bit-15 and going to Bit-0,
+            // denotes the position of the last match.
+            let last_match = 31 - mask.leading_zeros() as usize;
+            return Some(chunk + last_match - start);
+        }
+
+        if chunk == first_chunk {
+            return None;
+        }
+        chunk -= BYTES_PER_OPERATION;
+    }
+}
+
 struct PackedCompare<T, const CONTROL_BYTE: i32>(T);
 impl<T, const CONTROL_BYTE: i32> PackedCompare<T, CONTROL_BYTE>
 where
@@ -137,6 +242,65 @@ where
         }
     }
 
+    /// Compares an entire 16-byte chunk, returning one bit per byte.
+    ///
+    /// The chunk must be 16-byte aligned so that the read cannot
+    /// cross a page boundary.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    unsafe fn mask(&self, chunk: *const u8) -> u16 {
+        debug_assert_eq!(0, chunk as usize % BYTES_PER_OPERATION);
+
+        let haystack = _mm_loadu_si128(chunk as *const __m128i);
+
+        let mask = _mm_cmpestrm(
+            self.0.needle(),
+            self.0.needle_len(),
+            haystack,
+            BYTES_PER_OPERATION as i32,
+            CONTROL_BYTE,
+        );
+        _mm_extract_epi16(mask, 0) as u16
+    }
+
+    /// Searches four consecutive 16-byte chunks, branching only
+    /// once unless one of them matches.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    unsafe fn find_in_block(&self, block: *const u8) -> Option<usize> {
+        let location = |chunk| {
+            let haystack = _mm_loadu_si128(block.add(chunk * BYTES_PER_OPERATION) as *const __m128i);
+            _mm_cmpestri(
+                self.0.needle(),
+                self.0.needle_len(),
+                haystack,
+                BYTES_PER_OPERATION as i32,
+                CONTROL_BYTE,
+            )
+        };
+        let locations = [location(0), location(1), location(2), location(3)];
+
+        // A chunk without a match reports 16, so every bit other
+        // than that one stays clear unless something matched.
+        if locations.iter().fold(!0, |all, &l| all & l) == 16 {
+            return None;
+        }
+
+        locations
+            .iter()
+            .enumerate()
+            .find(|&(_, &l)| l < 16)
+            .map(|(chunk, &l)| chunk * BYTES_PER_OPERATION + l as usize)
+    }
+
+    /// Compares an entire 16-byte aligned chunk, clearing the bits
+    /// of any bytes outside of the haystack `start..end`.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    unsafe fn mask_within(&self, chunk: usize, start: usize, end: usize) -> u32 {
+        within(self.mask(chunk as *const u8), chunk, start, end)
+    }
+
     #[inline]
     #[target_feature(enable = "sse4.2")]
     unsafe fn cmpestri(&self, haystack: *const u8, haystack_len: i32) -> Option<usize> {
@@ -165,6 +329,26 @@ where
     }
 }
 
+/// Clears the bits of a 16-byte aligned chunk's mask that belong to
+/// bytes outside of the haystack `start..end`.
+#[inline]
+pub fn within(mask: u16, chunk: usize, start: usize, end: usize) -> u32 {
+    let mut mask = u32::from(mask);
+
+    // Remove any matches after the end of the haystack
+    let trailing = end - chunk;
+    if trailing < BYTES_PER_OPERATION {
+        mask &= (1 << trailing) - 1;
+    }
+
+    // Remove any matches before the start of the haystack
+    if chunk < start {
+        mask &= !((1 << (start - chunk)) - 1);
+    }
+
+    mask
+}
+
 #[derive(Debug)]
 struct Misalignment<'a> {
     leading: &'a [u8],
@@ -194,7 +378,7 @@ impl<'a> Misalignment<'a> {
             return None;
         }
 
-        let aligned_end = unsafe { aligned_start.offset(BYTES_PER_OPERATION as isize) };
+        let aligned_end = unsafe { aligned_start.add(BYTES_PER_OPERATION) };
 
         let leading_junk = haystack.as_ptr() as usize - aligned_start as usize;
         let leading_len = min(haystack.len() + leading_junk, BYTES_PER_OPERATION);
@@ -221,7 +405,7 @@ pub struct Bytes {
 }
 
 impl Bytes {
-    pub /* const */ fn new(bytes: [u8; 16], needle_len: i32) -> Self {
+    pub const fn new(bytes: [u8; 16], needle_len: i32) -> Self {
         Bytes {
             needle: unsafe { TransmuteToSimd { bytes }.simd },
             needle_len,
@@ -233,9 +417,289 @@ impl Bytes {
     pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
         find(PackedCompare::<_, 0>(self), haystack)
     }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        rfind(PackedCompare::<_, 0>(self), haystack)
+    }
+
+    /// Only the bytes inside of the haystack are negated; anything
+    /// past a short final chunk still counts as "not found".
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        find(PackedCompare::<_, _SIDD_MASKED_NEGATIVE_POLARITY>(self), haystack)
+    }
+
+    /// Any bytes of the aligned chunks outside of the haystack are
+    /// negated too, but `rfind` clears them from the mask.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        rfind(PackedCompare::<_, _SIDD_MASKED_NEGATIVE_POLARITY>(self), haystack)
+    }
+
+    pub fn find_iter<'b, 'h>(&'b self, haystack: &'h [u8]) -> FindIter<'b, 'h> {
+        FindIter::new(self, haystack)
+    }
+}
+
+/// Visits every match in a haystack, from either end.
+///
+/// Like [`rfind`], this only reads 16-byte aligned chunks. Each
+/// chunk's mask is kept around and drained one bit at a time before
+/// the next chunk is compared.
+pub struct FindIter<'b, 'h> {
+    bytes: &'b Bytes,
+    haystack: &'h [u8],
+    // The chunks in `next_chunk..next_back_chunk` have not been
+    // compared yet.
+    next_chunk: usize,
+    next_back_chunk: usize,
+    front_chunk: usize,
+    front_mask: u32,
+    back_chunk: usize,
+    back_mask: u32,
+}
+
+impl<'b, 'h> FindIter<'b, 'h> {
+    fn new(bytes: &'b Bytes, haystack: &'h [u8]) -> Self {
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+
+        let (next_chunk, next_back_chunk) = if haystack.is_empty() {
+            (start, start)
+        } else {
+            (start & !0xF, ((end - 1) & !0xF) + BYTES_PER_OPERATION)
+        };
+
+        FindIter {
+            bytes,
+            haystack,
+            next_chunk,
+            next_back_chunk,
+            front_chunk: next_chunk,
+            front_mask: 0,
+            back_chunk: next_back_chunk,
+            back_mask: 0,
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    unsafe fn mask_within(&self, chunk: usize) -> u32 {
+        let start = self.haystack.as_ptr() as usize;
+        let end = start + self.haystack.len();
+        PackedCompare::<_, 0>(self.bytes).mask_within(chunk, start, end)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn next(&mut self) -> Option<usize> {
+        let start = self.haystack.as_ptr() as usize;
+
+        loop {
+            if self.front_mask != 0 {
+                let first_match = self.front_mask.trailing_zeros() as usize;
+                self.front_mask &= self.front_mask - 1;
+                return Some(self.front_chunk + first_match - start);
+            }
+
+            if self.next_chunk == self.next_back_chunk {
+                // Every chunk has been compared; anything left over
+                // is waiting in the back.
+                if self.back_mask != 0 {
+                    let first_match = self.back_mask.trailing_zeros() as usize;
+                    self.back_mask &= self.back_mask - 1;
+                    return Some(self.back_chunk + first_match - start);
+                }
+                return None;
+            }
+
+            self.front_chunk = self.next_chunk;
+            self.front_mask = self.mask_within(self.front_chunk);
+            self.next_chunk += BYTES_PER_OPERATION;
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn next_back(&mut self) -> Option<usize> {
+        let start = self.haystack.as_ptr() as usize;
+
+        loop {
+            if self.back_mask != 0 {
+                let last_match = 31 - self.back_mask.leading_zeros() as usize;
+                self.back_mask &= !(1 << last_match);
+                return Some(self.back_chunk + last_match - start);
+            }
+
+            if self.next_chunk == self.next_back_chunk {
+                // Every chunk has been compared; anything left over
+                // is waiting in the front.
+                if self.front_mask != 0 {
+                    let last_match = 31 - self.front_mask.leading_zeros() as usize;
+                    self.front_mask &= !(1 << last_match);
+                    return Some(self.front_chunk + last_match - start);
+                }
+                return None;
+            }
+
+            self.next_back_chunk -= BYTES_PER_OPERATION;
+            self.back_chunk = self.next_back_chunk;
+            self.back_mask = self.mask_within(self.back_chunk);
+        }
+    }
+}
+
+impl PackedCompareControl for &Bytes {
+    fn needle(&self) -> __m128i {
+        self.needle
+    }
+    fn needle_len(&self) -> i32 {
+        self.needle_len
+    }
+}
+
+/// The most bytes that [`ByteSet`] will pack into needles; any more
+/// than this and a lookup table is faster.
+pub const BYTE_SET_MAX_LEN: usize = 4 * BYTES_PER_OPERATION;
+
+/// Searches for more than 16 bytes by comparing each chunk against
+/// several packed needles and combining the resulting masks.
+pub struct ByteSet {
+    parts: [Bytes; BYTE_SET_MAX_LEN / BYTES_PER_OPERATION],
+    n_parts: usize,
+}
+
+impl ByteSet {
+    /// The bytes should be unique, as each duplicate takes up a slot.
+    pub fn new(bytes: &[u8]) -> Self {
+        assert!(bytes.len() <= BYTE_SET_MAX_LEN, "Cannot pack more than {} bytes", BYTE_SET_MAX_LEN);
+
+        let mut needles = [[0; 16]; BYTE_SET_MAX_LEN / BYTES_PER_OPERATION];
+        let mut needle_lens = [0; BYTE_SET_MAX_LEN / BYTES_PER_OPERATION];
+        for (i, part) in bytes.chunks(BYTES_PER_OPERATION).enumerate() {
+            needles[i][..part.len()].copy_from_slice(part);
+            needle_lens[i] = part.len() as i32;
+        }
+
+        ByteSet {
+            parts: [
+                Bytes::new(needles[0], needle_lens[0]),
+                Bytes::new(needles[1], needle_lens[1]),
+                Bytes::new(needles[2], needle_lens[2]),
+                Bytes::new(needles[3], needle_lens[3]),
+            ],
+            n_parts: bytes.chunks(BYTES_PER_OPERATION).count(),
+        }
+    }
+
+    /// Like [`rfind`], this only reads 16-byte aligned chunks.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let last_chunk = (end - 1) & !0xF;
+        let mut chunk = start & !0xF;
+
+        loop {
+            let mask = within(self.mask(chunk), chunk, start, end);
+            if mask != 0 {
+                return Some(chunk + mask.trailing_zeros() as usize - start);
+            }
+
+            if chunk == last_chunk {
+                return None;
+            }
+            chunk += BYTES_PER_OPERATION;
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let first_chunk = start & !0xF;
+        let mut chunk = (end - 1) & !0xF;
+
+        loop {
+            let mask = within(self.mask(chunk), chunk, start, end);
+            if mask != 0 {
+                let last_match = 31 - mask.leading_zeros() as usize;
+                return Some(chunk + last_match - start);
+            }
+
+            if chunk == first_chunk {
+                return None;
+            }
+            chunk -= BYTES_PER_OPERATION;
+        }
+    }
+
+    /// Combines the masks of every part for one aligned chunk.
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    unsafe fn mask(&self, chunk: usize) -> u16 {
+        let mut mask = 0;
+        for part in &self.parts[..self.n_parts] {
+            mask |= PackedCompare::<_, 0>(part).mask(chunk as *const u8);
+        }
+        mask
+    }
+}
+
+pub struct ByteRanges {
+    needle: __m128i,
+    needle_len: i32,
+}
+
+impl ByteRanges {
+    /// Each pair of bytes is an inclusive range of `low, high`.
+    pub const fn new(ranges: [u8; 16], needle_len: i32) -> Self {
+        ByteRanges {
+            needle: unsafe { TransmuteToSimd { bytes: ranges }.simd },
+            needle_len,
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        find(PackedCompare::<_, _SIDD_CMP_RANGES>(self), haystack)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        rfind(PackedCompare::<_, _SIDD_CMP_RANGES>(self), haystack)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        find(PackedCompare::<_, { _SIDD_CMP_RANGES | _SIDD_MASKED_NEGATIVE_POLARITY }>(self), haystack)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        rfind(PackedCompare::<_, { _SIDD_CMP_RANGES | _SIDD_MASKED_NEGATIVE_POLARITY }>(self), haystack)
+    }
 }
 
-impl<'b> PackedCompareControl for &'b Bytes {
+impl PackedCompareControl for &ByteRanges {
     fn needle(&self) -> __m128i {
         self.needle
     }
@@ -245,20 +709,20 @@ impl<'b> PackedCompareControl for &'b Bytes {
 }
 
 pub struct ByteSubstring<'a> {
-    complete_needle: &'a [u8],
+    two_way: TwoWay<'a>,
     needle: __m128i,
     needle_len: i32,
 }
 
 impl<'a> ByteSubstring<'a> {
-    pub /* const */ fn new(needle: &'a[u8]) -> Self {
-        use std::cmp;
+    pub const fn new(needle: &'a[u8]) -> Self {
+        // Compare the part of the needle that starts at its rarest
+        // byte, as common bytes would produce many false candidates.
+        let two_way = TwoWay::new(needle);
+        let (simd_needle, len) = two_way.anchor_prefix();
 
-        let mut simd_needle = [0; 16];
-        let len = cmp::min(simd_needle.len(), needle.len());
-        simd_needle[..len].copy_from_slice(&needle[..len]);
         ByteSubstring {
-            complete_needle: needle,
+            two_way,
             needle: unsafe { TransmuteToSimd { bytes: simd_needle }.simd },
             needle_len: len as i32,
         }
@@ -266,26 +730,30 @@ impl<'a> ByteSubstring<'a> {
 
     #[cfg(feature = "pattern")]
     pub fn needle_len(&self) -> usize {
-        self.complete_needle.len()
+        self.two_way.needle().len()
     }
 
+    // Superseded by the AVX2 implementation when that is guaranteed.
+    #[cfg_attr(target_feature = "avx2", allow(dead_code))]
     #[inline]
     #[target_feature(enable = "sse4.2")]
     pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
-        let mut offset = 0;
-
-        while let Some(idx) = find(PackedCompare::<_, _SIDD_CMP_EQUAL_ORDERED>(self), &haystack[offset..]) {
-            let abs_offset = offset + idx;
-            // Found a match, but is it really?
-            if haystack[abs_offset..].starts_with(self.complete_needle) {
-                return Some(abs_offset);
-            }
-
-            // Skip past this false positive
-            offset += idx + 1;
-        }
+        // Candidates may be partial matches at the end of the slice,
+        // which the Two-Way search will reject.
+        self.two_way.find(haystack, |candidates| {
+            find(PackedCompare::<_, _SIDD_CMP_EQUAL_ORDERED>(self), candidates)
+        })
+    }
 
-        None
+    #[inline]
+    #[target_feature(enable = "sse4.2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        // The mask is computed over whole chunks, so bytes after the
+        // candidates still take part in the comparison. A real match
+        // only ever compares bytes of the haystack.
+        self.two_way.rfind(haystack, |candidates| {
+            rfind(PackedCompare::<_, _SIDD_CMP_EQUAL_ORDERED>(self), candidates)
+        })
     }
 }
 
@@ -301,23 +769,27 @@ impl<'a, 'b> PackedCompareControl for &'b ByteSubstring<'a> {
 // TODO: Does x86 actually support this instruction?
 
 #[cfg(test)]
-mod test {
-    use proptest::prelude::*;
-    use std::{fmt, str};
+pub(crate) mod test {
+    use proptest::{collection::vec, prelude::*};
+    use std::{collections::VecDeque, fmt, iter, str};
     use memmap::MmapMut;
     use region::Protection;
 
     use super::*;
+    use horspool::LONG_NEEDLE_MIN_LEN;
 
-    lazy_static! {
-        static ref SPACE: Bytes = simd_bytes!(b' ');
-        static ref XML_DELIM_3: Bytes = simd_bytes!(b'<', b'>', b'&');
-        static ref XML_DELIM_5: Bytes = simd_bytes!(b'<', b'>', b'&', b'\'', b'"');
-    }
+    static SPACE: Bytes = simd_bytes!(b' ');
+    static XML_DELIM_3: Bytes = simd_bytes!(b'<', b'>', b'&');
+    static XML_DELIM_5: Bytes = simd_bytes!(b'<', b'>', b'&', b'\'', b'"');
 
     trait SliceFindPolyfill<T> {
         fn find_any(&self, needles: &[T]) -> Option<usize>;
+        fn rfind_any(&self, needles: &[T]) -> Option<usize>;
+        fn find_not_any(&self, needles: &[T]) -> Option<usize>;
+        fn rfind_not_any(&self, needles: &[T]) -> Option<usize>;
+        fn find_all(&self, needles: &[T]) -> Vec<usize>;
         fn find_seq(&self, needle: &[T]) -> Option<usize>;
+        fn rfind_seq(&self, needle: &[T]) -> Option<usize>;
     }
 
     impl<T> SliceFindPolyfill<T> for [T]
@@ -328,9 +800,29 @@ mod test {
             self.iter().position(|c| needles.contains(c))
         }
 
+        fn rfind_any(&self, needles: &[T]) -> Option<usize> {
+            self.iter().rposition(|c| needles.contains(c))
+        }
+
+        fn find_not_any(&self, needles: &[T]) -> Option<usize> {
+            self.iter().position(|c| !needles.contains(c))
+        }
+
+        fn rfind_not_any(&self, needles: &[T]) -> Option<usize> {
+            self.iter().rposition(|c| !needles.contains(c))
+        }
+
+        fn find_all(&self, needles: &[T]) -> Vec<usize> {
+            (0..self.len()).filter(|&i| needles.contains(&self[i])).collect()
+        }
+
         fn find_seq(&self, needle: &[T]) -> Option<usize> {
             (0..self.len()).find(|&l| self[l..].starts_with(needle))
         }
+
+        fn rfind_seq(&self, needle: &[T]) -> Option<usize> {
+            (0..self.len()).rev().find(|&l| self[l..].starts_with(needle))
+        }
     }
 
     struct Haystack {
@@ -391,6 +883,30 @@ mod test {
             .boxed()
     }
 
+    impl Needle {
+        fn in_ranges(&self, b: u8) -> bool {
+            self.as_slice()
+                .chunks(2)
+                .any(|r| r[0] <= b && b <= r[1])
+        }
+    }
+
+    /// Creates an array of up to 8 ranges. The low end of each range
+    /// is usually smaller than the high end, but not always.
+    fn ranges() -> BoxedStrategy<Needle> {
+        (vec(any::<(u8, u8)>(), 0..=8), any::<bool>())
+            .prop_map(|(pairs, sorted)| {
+                let mut data = [0; 16];
+                for (i, &(a, b)) in pairs.iter().enumerate() {
+                    let (lo, hi) = if sorted { (a.min(b), a.max(b)) } else { (a, b) };
+                    data[2 * i] = lo;
+                    data[2 * i + 1] = hi;
+                }
+                Needle { data, len: 2 * pairs.len() }
+            })
+            .boxed()
+    }
+
     proptest! {
         #[test]
         fn works_as_find_does_for_up_to_and_including_16_bytes(
@@ -413,6 +929,112 @@ mod test {
             let them = haystack.find_any(needle.as_slice());
             assert_eq!(us, them);
         }
+
+        #[test]
+        fn works_as_find_not_does_for_various_memory_offsets(
+            (needle, haystack) in (needle(), haystack())
+        ) {
+            let haystack = haystack.with_start();
+
+            let bytes = Bytes::new(needle.data, needle.len as i32);
+
+            let us = unsafe { bytes.find_not(haystack) };
+            let them = haystack.find_not_any(needle.as_slice());
+            assert_eq!(us, them);
+
+            let us = unsafe { bytes.rfind_not(haystack) };
+            let them = haystack.rfind_not_any(needle.as_slice());
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn byte_sets_work_as_find_does(
+            (needle, haystack) in (vec(any::<u8>(), 0..=BYTE_SET_MAX_LEN), haystack())
+        ) {
+            let haystack = haystack.with_start();
+
+            let set = ByteSet::new(&needle);
+
+            let us = unsafe { set.find(haystack) };
+            let them = haystack.find_any(&needle);
+            assert_eq!(us, them);
+
+            let us = unsafe { set.rfind(haystack) };
+            let them = haystack.rfind_any(&needle);
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn byte_ranges_work_as_find_does(
+            (needle, haystack) in (ranges(), haystack())
+        ) {
+            let haystack = haystack.with_start();
+            let ranges = ByteRanges::new(needle.data, needle.len as i32);
+
+            let us = unsafe { ranges.find(haystack) };
+            let them = haystack.iter().position(|&b| needle.in_ranges(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { ranges.rfind(haystack) };
+            let them = haystack.iter().rposition(|&b| needle.in_ranges(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { ranges.find_not(haystack) };
+            let them = haystack.iter().position(|&b| !needle.in_ranges(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { ranges.rfind_not(haystack) };
+            let them = haystack.iter().rposition(|&b| !needle.in_ranges(b));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn find_iter_visits_every_match(
+            (needle, haystack) in (needle(), haystack())
+        ) {
+            let haystack = haystack.with_start();
+            let bytes = Bytes::new(needle.data, needle.len as i32);
+
+            let mut iter = bytes.find_iter(haystack);
+            let us: Vec<_> = unsafe { iter::from_fn(|| iter.next()).collect() };
+            let them = haystack.find_all(needle.as_slice());
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn find_iter_visits_every_match_from_either_end(
+            (needle, haystack, from_back) in (needle(), haystack(), any::<Vec<bool>>())
+        ) {
+            let haystack = haystack.with_start();
+            let bytes = Bytes::new(needle.data, needle.len as i32);
+
+            let mut iter = bytes.find_iter(haystack);
+            let mut them = VecDeque::from(haystack.find_all(needle.as_slice()));
+            for from_back in from_back.into_iter().chain(iter::repeat(false)) {
+                let (us, them) = unsafe {
+                    if from_back {
+                        (iter.next_back(), them.pop_back())
+                    } else {
+                        (iter.next(), them.pop_front())
+                    }
+                };
+                assert_eq!(us, them);
+                if us.is_none() {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        fn works_as_rfind_does_for_various_memory_offsets(
+            (needle, haystack) in (needle(), haystack())
+        ) {
+            let haystack = haystack.with_start();
+
+            let us = unsafe { Bytes::new(needle.data, needle.len as i32).rfind(haystack) };
+            let them = haystack.rfind_any(needle.as_slice());
+            assert_eq!(us, them);
+        }
     }
 
     #[test]
@@ -492,6 +1114,46 @@ mod test {
         }
     }
 
+    #[test]
+    fn space_is_found_from_the_end() {
+        unsafe {
+            assert_eq!(Some(0), SPACE.rfind(b" "));
+            assert_eq!(Some(0), SPACE.rfind(b" 0"));
+            assert_eq!(Some(0), SPACE.rfind(b" 0123456789ABCDE"));
+            assert_eq!(Some(0), SPACE.rfind(b" 0123456789ABCDEF"));
+            assert_eq!(Some(0), SPACE.rfind(b" 0123456789ABCDEFG"));
+            assert_eq!(Some(16), SPACE.rfind(b" 0123456789ABCDE F"));
+            assert_eq!(Some(17), SPACE.rfind(b" 0123456789ABCDEF "));
+            assert_eq!(None, SPACE.rfind(b""));
+            assert_eq!(None, SPACE.rfind(b"0123456789ABCDEFG"));
+        }
+    }
+
+    #[test]
+    fn space_is_skipped() {
+        unsafe {
+            assert_eq!(None, SPACE.find_not(b""));
+            assert_eq!(None, SPACE.find_not(b" "));
+            assert_eq!(Some(0), SPACE.find_not(b"0 "));
+            assert_eq!(Some(1), SPACE.find_not(b" 0"));
+            assert_eq!(Some(15), SPACE.find_not(b"               0"));
+            assert_eq!(Some(16), SPACE.find_not(b"                0"));
+            assert_eq!(None, SPACE.find_not(b"                 "));
+        }
+    }
+
+    #[test]
+    fn control_characters_are_found() {
+        let control = ByteRanges::new([0x00, 0x1F, 0x7F, 0x7F, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 4);
+        unsafe {
+            assert_eq!(Some(5), control.find(b"hello\nworld"));
+            assert_eq!(Some(16), control.find(b"0123456789ABCDEF\x7F"));
+            assert_eq!(Some(0), control.find(b"\0"));
+            assert_eq!(None, control.find(b" ~\x80\xFF"));
+            assert_eq!(Some(1), control.find_not(b"\t \r\n"));
+        }
+    }
+
     #[test]
     fn works_on_nonaligned_beginnings() {
         unsafe {
@@ -594,6 +1256,51 @@ mod test {
             let them = haystack.find_seq(&needle);
             assert_eq!(us, them);
         }
+
+        #[test]
+        fn works_as_rfind_does_for_byte_substrings(
+            (needle, haystack) in (any::<Vec<u8>>(), any::<Vec<u8>>())
+        ) {
+            let us = unsafe {
+                let s = ByteSubstring::new(&needle);
+                s.rfind(&haystack)
+            };
+            let them = haystack.rfind_seq(&needle);
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_as_find_does_for_long_byte_substrings(
+            (needle, haystack) in long_needle_and_haystack()
+        ) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = unsafe { substring.find(&haystack) };
+            let them = haystack.find_seq(&needle);
+            assert_eq!(us, them);
+
+            let us = unsafe { substring.rfind(&haystack) };
+            let them = haystack.rfind_seq(&needle);
+            assert_eq!(us, them);
+        }
+    }
+
+    /// Creates a needle longer than 16 bytes and a haystack, which
+    /// often contains the needle. A small alphabet makes repetitive
+    /// needles and partial matches common.
+    pub(crate) fn long_needle_and_haystack() -> BoxedStrategy<(Vec<u8>, Vec<u8>)> {
+        let text = |len| vec(prop_oneof![Just(b'<'), Just(b'a'), Just(b'/'), Just(b'>')], len);
+
+        (text(LONG_NEEDLE_MIN_LEN..300), text(0..200), text(0..200), any::<bool>())
+            .prop_map(|(needle, before, after, include)| {
+                let mut haystack = before;
+                if include {
+                    haystack.extend_from_slice(&needle);
+                }
+                haystack.extend(after);
+                (needle, haystack)
+            })
+            .boxed()
     }
 
     #[test]
@@ -660,6 +1367,36 @@ mod test {
         };
     }
 
+    #[test]
+    fn byte_substring_with_a_common_prefix_is_found() {
+        // The filter looks for "Q", so every candidate is checked
+        // around it.
+        let mut haystack = vec![b' '; 100];
+        haystack[50] = b'Q';
+        haystack[90] = b'Q';
+
+        unsafe {
+            let substr = ByteSubstring::new(b"        Q ");
+            assert_eq!(Some(42), substr.find(&haystack));
+            assert_eq!(Some(82), substr.rfind(&haystack));
+            assert_eq!(None, substr.find(&haystack[45..90]));
+            assert_eq!(None, substr.rfind(&haystack[..50]));
+        }
+    }
+
+    #[test]
+    fn byte_substring_is_found_from_the_end() {
+        unsafe {
+            let substr = ByteSubstring::new(b"zz");
+            assert_eq!(Some(0), substr.rfind(b"zz"));
+            assert_eq!(Some(1), substr.rfind(b"zzz"));
+            assert_eq!(Some(15), substr.rfind(b"zz0123456789ABCzz"));
+            assert_eq!(Some(16), substr.rfind(b"zz0123456789ABCDzz"));
+            assert_eq!(Some(0), substr.rfind(b"zz0123456789ABCDEF"));
+            assert_eq!(None, substr.rfind(b"z0123456789ABCDEFz"));
+        }
+    }
+
     #[test]
     fn byte_substring_needle_is_longer_than_16_bytes() {
         unsafe {
@@ -669,7 +1406,7 @@ mod test {
         }
     }
 
-    fn with_guarded_string(value: &str, f: impl FnOnce(&str)) {
+    pub(crate) fn with_guarded_string(value: &str, f: impl FnOnce(&str)) {
         // Allocate a string that ends directly before a
         // read-protected page.
 
@@ -719,6 +1456,50 @@ mod test {
         });
     }
 
+    #[test]
+    fn matches_are_found_in_every_chunk_of_a_block() {
+        let mut haystack = vec![b'a'; 4 * BYTES_PER_BLOCK];
+        let needle = simd_bytes!(b'z', b'q');
+        let substring = ByteSubstring::new(b"zq");
+
+        for i in 0..haystack.len() - 1 {
+            haystack[i] = b'z';
+            haystack[i + 1] = b'q';
+
+            // Start at every alignment
+            for start in 0..BYTES_PER_OPERATION {
+                let haystack = &haystack[start..];
+                unsafe {
+                    assert_eq!(haystack.find_any(b"zq"), needle.find(haystack));
+                    assert_eq!(haystack.find_seq(b"zq"), substring.find(haystack));
+                }
+            }
+
+            haystack[i] = b'a';
+            haystack[i + 1] = b'a';
+        }
+    }
+
+    #[test]
+    fn short_haystacks_work_at_page_boundary() {
+        with_guarded_string("0123456789abcdef", |text| {
+            let needle = simd_bytes!(b'0', b'f');
+            let substring = ByteSubstring::new(b"ef");
+
+            // Check every haystack shorter than a chunk, including
+            // those that end right before the protected page
+            for start in 0..text.len() {
+                for end in start + 1..text.len().min(start + BYTES_PER_OPERATION) + 1 {
+                    let haystack = &text.as_bytes()[start..end];
+                    unsafe {
+                        assert_eq!(haystack.find_any(b"0f"), needle.find(haystack));
+                        assert_eq!(haystack.find_seq(b"ef"), substring.find(haystack));
+                    }
+                }
+            }
+        });
+    }
+
     #[test]
     fn does_not_access_memory_after_haystack_when_haystack_is_multiple_of_16_bytes_and_no_match() {
         // For now, this test failing crashes the whole test
@@ -733,4 +1514,67 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn rfind_works_at_page_boundary() {
+        // Searching from the end starts with the chunk holding the
+        // last byte, so that's the one most likely to wander off the
+        // end of the page.
+        with_guarded_string("0123456789abcdef", |text| {
+            // Will search for the last char
+            let needle = simd_bytes!(b'f');
+
+            // Check all suffixes of our 16-byte string
+            for offset in 0..text.len() {
+                let tail = &text[offset..];
+                unsafe {
+                    assert_eq!(Some(tail.len() - 1), needle.rfind(tail.as_bytes()));
+                }
+            }
+
+            // Will search for a char not present
+            let needle = simd_bytes!(b'z');
+
+            unsafe {
+                assert_eq!(None, needle.rfind(text.as_bytes()));
+            }
+        });
+    }
+
+    #[test]
+    fn find_iter_works_at_page_boundary() {
+        with_guarded_string("0123456789abcdef", |text| {
+            let needle = simd_bytes!(b'0', b'f');
+
+            // Check all suffixes of our 16-byte string
+            for offset in 0..text.len() {
+                let tail = &text[offset..];
+                let mut iter = needle.find_iter(tail.as_bytes());
+                unsafe {
+                    assert_eq!(Some(tail.len() - 1), iter.next_back());
+                    assert_eq!(offset == 0, iter.next().is_some());
+                    assert_eq!(None, iter.next());
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn find_not_works_at_page_boundary() {
+        with_guarded_string("0123456789abcdef", |text| {
+            // Will skip everything but the last char
+            let needle = simd_bytes!(
+                b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7',
+                b'8', b'9', b'a', b'b', b'c', b'd', b'e'
+            );
+
+            // Check all suffixes of our 16-byte string
+            for offset in 0..text.len() {
+                let tail = &text[offset..];
+                unsafe {
+                    assert_eq!(Some(tail.len() - 1), needle.find_not(tail.as_bytes()));
+                }
+            }
+        });
+    }
 }
@@ -0,0 +1,340 @@
+// # Warning
+//
+// Everything in this module assumes that the SSE2 feature is
+// available. Every x86_64 CPU has it.
+
+use std::arch::x86_64::{
+    __m128i, _mm_cmpeq_epi8, _mm_load_si128, _mm_movemask_epi8, _mm_or_si128, _mm_set1_epi8,
+    _mm_setzero_si128,
+};
+
+use simd::within;
+use table::ByteList;
+#[cfg(not(target_feature = "sse4.2"))]
+use twoway::TwoWay;
+
+const BYTES_PER_OPERATION: usize = 16;
+
+/// Compares each chunk against every byte in the set, one broadcast
+/// byte at a time, and combines the results.
+#[derive(Copy, Clone)]
+pub struct Bytes<'s> {
+    set: &'s ByteList,
+}
+
+impl<'s> Bytes<'s> {
+    pub const fn new(set: &'s ByteList) -> Self {
+        Bytes { set }
+    }
+
+    /// Compares an entire 16-byte chunk, returning one bit per byte.
+    ///
+    /// The chunk must be 16-byte aligned so that the read cannot
+    /// cross a page boundary.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn mask(&self, chunk: *const u8) -> u16 {
+        debug_assert_eq!(0, chunk as usize % BYTES_PER_OPERATION);
+
+        let haystack = _mm_load_si128(chunk as *const __m128i);
+
+        let mut matched = _mm_setzero_si128();
+        for &b in self.set.as_slice() {
+            matched = _mm_or_si128(matched, _mm_cmpeq_epi8(haystack, _mm_set1_epi8(b as i8)));
+        }
+        _mm_movemask_epi8(matched) as u16
+    }
+
+    /// Reads only 16-byte aligned chunks, like the `PCMPxSTRx`
+    /// searches.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn find_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u16) -> u16,
+    {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let last_chunk = (end - 1) & !0xF;
+        let mut chunk = start & !0xF;
+
+        loop {
+            let found = within(mask(self.mask(chunk as *const u8)), chunk, start, end);
+            if found != 0 {
+                return Some(chunk + found.trailing_zeros() as usize - start);
+            }
+
+            if chunk == last_chunk {
+                return None;
+            }
+            chunk += BYTES_PER_OPERATION;
+        }
+    }
+
+    // Superseded by the AVX2 implementation when that is guaranteed.
+    #[cfg_attr(target_feature = "avx2", allow(dead_code))]
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| mask)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_mask(haystack, |mask| !mask)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| mask)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn rfind_not(&self, haystack: &[u8]) -> Option<usize> {
+        self.rfind_mask(haystack, |mask| !mask)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn rfind_mask<M>(&self, haystack: &[u8], mask: M) -> Option<usize>
+    where
+        M: Fn(u16) -> u16,
+    {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let first_chunk = start & !0xF;
+        let mut chunk = (end - 1) & !0xF;
+
+        loop {
+            let found = within(mask(self.mask(chunk as *const u8)), chunk, start, end);
+            if found != 0 {
+                let last_match = 31 - found.leading_zeros() as usize;
+                return Some(chunk + last_match - start);
+            }
+
+            if chunk == first_chunk {
+                return None;
+            }
+            chunk -= BYTES_PER_OPERATION;
+        }
+    }
+
+    #[cfg(not(target_feature = "sse4.2"))]
+    pub fn find_iter<'h>(self, haystack: &'h [u8]) -> FindIter<'s, 'h> {
+        FindIter {
+            bytes: self,
+            haystack,
+            front: 0,
+            back: haystack.len(),
+        }
+    }
+}
+
+/// Visits every match in a haystack, from either end, by searching
+/// what is left between the previous matches.
+#[cfg(not(target_feature = "sse4.2"))]
+pub struct FindIter<'s, 'h> {
+    bytes: Bytes<'s>,
+    haystack: &'h [u8],
+    front: usize,
+    back: usize,
+}
+
+#[cfg(not(target_feature = "sse4.2"))]
+impl<'s, 'h> FindIter<'s, 'h> {
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn next(&mut self) -> Option<usize> {
+        let idx = self.bytes.find(&self.haystack[self.front..self.back])?;
+        let idx = self.front + idx;
+        self.front = idx + 1;
+        Some(idx)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn next_back(&mut self) -> Option<usize> {
+        let idx = self.bytes.rfind(&self.haystack[self.front..self.back])?;
+        let idx = self.front + idx;
+        self.back = idx;
+        Some(idx)
+    }
+}
+
+/// Uses the rarest byte of the needle to find candidates, then checks
+/// each candidate with the Two-Way algorithm.
+#[cfg(not(target_feature = "sse4.2"))]
+pub struct ByteSubstring<'a> {
+    two_way: TwoWay<'a>,
+    anchor: ByteList,
+}
+
+#[cfg(not(target_feature = "sse4.2"))]
+impl<'a> ByteSubstring<'a> {
+    pub const fn new(needle: &'a [u8]) -> Self {
+        let two_way = TwoWay::new(needle);
+
+        let mut bytes = [0; 16];
+        let len = if let Some(anchor) = two_way.anchor_byte() {
+            bytes[0] = anchor;
+            1
+        } else {
+            0
+        };
+
+        ByteSubstring {
+            two_way,
+            anchor: ByteList::new(bytes, len),
+        }
+    }
+
+    #[cfg(feature = "pattern")]
+    pub fn needle_len(&self) -> usize {
+        self.two_way.needle().len()
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.find(haystack, |candidates| Bytes::new(&self.anchor).find(candidates))
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        self.two_way.rfind(haystack, |candidates| Bytes::new(&self.anchor).rfind(candidates))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    #[cfg(not(target_feature = "sse4.2"))]
+    use simd::test::long_needle_and_haystack;
+    #[cfg(not(target_feature = "sse4.2"))]
+    use std::{collections::VecDeque, iter};
+
+    /// Creates a set of bytes and an offset inside them.
+    fn haystack() -> BoxedStrategy<(Vec<u8>, usize)> {
+        any::<Vec<u8>>()
+            .prop_flat_map(|data| {
+                let len = 0..=data.len();
+                (Just(data), len)
+            })
+            .boxed()
+    }
+
+    /// Creates an array and the number of valid values
+    fn needle() -> BoxedStrategy<([u8; 16], usize)> {
+        (any::<[u8; 16]>(), 0..=16_usize).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does(
+            ((needle, len), (haystack, start)) in (needle(), haystack())
+        ) {
+            let haystack = &haystack[start..];
+            let set = ByteList::new(needle, len);
+            let bytes = Bytes::new(&set);
+            let needle = &needle[..len];
+
+            let us = unsafe { bytes.find(haystack) };
+            let them = haystack.iter().position(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { bytes.rfind(haystack) };
+            let them = haystack.iter().rposition(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { bytes.find_not(haystack) };
+            let them = haystack.iter().position(|b| !needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { bytes.rfind_not(haystack) };
+            let them = haystack.iter().rposition(|b| !needle.contains(b));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        #[cfg(not(target_feature = "sse4.2"))]
+        fn find_iter_visits_every_match_from_either_end(
+            ((needle, len), (haystack, start), from_back) in (needle(), haystack(), any::<Vec<bool>>())
+        ) {
+            let haystack = &haystack[start..];
+            let set = ByteList::new(needle, len);
+            let bytes = Bytes::new(&set);
+            let needle = &needle[..len];
+
+            let mut expected: VecDeque<_> = (0..haystack.len()).filter(|&i| needle.contains(&haystack[i])).collect();
+            let mut matches = bytes.find_iter(haystack);
+            for from_back in from_back.into_iter().chain(iter::repeat(false)) {
+                let (us, them) = if from_back {
+                    (unsafe { matches.next_back() }, expected.pop_back())
+                } else {
+                    (unsafe { matches.next() }, expected.pop_front())
+                };
+                assert_eq!(us, them);
+                if them.is_none() {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        #[cfg(not(target_feature = "sse4.2"))]
+        fn works_as_find_does_for_byte_substrings(
+            (needle, haystack) in (any::<Vec<u8>>(), any::<Vec<u8>>())
+        ) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = unsafe { substring.find(&haystack) };
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = unsafe { substring.rfind(&haystack) };
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        #[cfg(not(target_feature = "sse4.2"))]
+        fn works_as_find_does_for_long_byte_substrings(
+            (needle, haystack) in long_needle_and_haystack()
+        ) {
+            let substring = ByteSubstring::new(&needle);
+
+            let us = unsafe { substring.find(&haystack) };
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = unsafe { substring.rfind(&haystack) };
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_feature = "sse4.2"))]
+    fn byte_substring_is_found() {
+        let substring = ByteSubstring::new(b"zz");
+        unsafe {
+            assert_eq!(Some(17), substring.find(b"z0123456789ABCDEFzz"));
+            assert_eq!(Some(0), substring.rfind(b"zz0123456789ABCDEFz"));
+            assert_eq!(None, substring.find(b"z"));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// # Warning
+//
+// Everything in this module assumes that the SSSE3 feature is available.
+
+use std::arch::x86_64::{
+    __m128i, _mm_and_si128, _mm_cmpeq_epi8, _mm_load_si128, _mm_movemask_epi8, _mm_or_si128,
+    _mm_set1_epi8, _mm_setr_epi8, _mm_shuffle_epi8, _mm_srli_epi16, _mm_xor_si128,
+};
+
+use simd::within;
+use table::ByteTable;
+
+const BYTES_PER_OPERATION: usize = 16;
+
+union TransmuteToSimd {
+    simd: __m128i,
+    bytes: [u8; 16],
+}
+
+/// Checks each byte against a 256-entry class using two `PSHUFB`
+/// lookups, one per nibble.
+///
+/// The low nibble selects a row of the class from one of two 16-byte
+/// tables: `low_rows` holds the bytes `0x00` to `0x7F` and
+/// `high_rows` holds `0x80` to `0xFF`. Each row has one bit for each
+/// value of the high nibble. The high nibble then selects which bit
+/// of the row to test.
+pub struct ByteClass {
+    low_rows: __m128i,
+    high_rows: __m128i,
+}
+
+impl ByteClass {
+    pub fn new(table: &ByteTable) -> Self {
+        let mut low_rows = [0; 16];
+        let mut high_rows = [0; 16];
+
+        for b in table.bytes() {
+            let row = usize::from(b & 0x0F);
+            let bit = 1 << (b >> 4 & 0x07);
+            if b < 0x80 {
+                low_rows[row] |= bit;
+            } else {
+                high_rows[row] |= bit;
+            }
+        }
+
+        ByteClass {
+            low_rows: unsafe { TransmuteToSimd { bytes: low_rows }.simd },
+            high_rows: unsafe { TransmuteToSimd { bytes: high_rows }.simd },
+        }
+    }
+
+    /// Classifies an entire 16-byte chunk, returning one bit per byte.
+    ///
+    /// The chunk must be 16-byte aligned so that the read cannot
+    /// cross a page boundary.
+    #[inline]
+    #[target_feature(enable = "ssse3")]
+    unsafe fn mask(&self, chunk: *const u8) -> u16 {
+        debug_assert_eq!(0, chunk as usize % BYTES_PER_OPERATION);
+
+        let haystack = _mm_load_si128(chunk as *const __m128i);
+
+        // `PSHUFB` produces a zero for any index with the top bit
+        // set. Keeping that bit around in the index means that only
+        // one of the two tables contributes a row for each byte.
+        let row_index = _mm_and_si128(haystack, _mm_set1_epi8(0x8F_u8 as i8));
+        let low = _mm_shuffle_epi8(self.low_rows, row_index);
+        let high = _mm_shuffle_epi8(self.high_rows, _mm_xor_si128(row_index, _mm_set1_epi8(0x80_u8 as i8)));
+        let row = _mm_or_si128(low, high);
+
+        let bit_index = _mm_and_si128(_mm_srli_epi16(haystack, 4), _mm_set1_epi8(0x0F));
+        let bit = _mm_shuffle_epi8(
+            _mm_setr_epi8(1, 2, 4, 8, 16, 32, 64, -128, 1, 2, 4, 8, 16, 32, 64, -128),
+            bit_index,
+        );
+
+        let matched = _mm_cmpeq_epi8(_mm_and_si128(row, bit), bit);
+        _mm_movemask_epi8(matched) as u16
+    }
+
+    /// Reads only 16-byte aligned chunks, like the `PCMPxSTRx`
+    /// searches.
+    #[inline]
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn find(&self, haystack: &[u8]) -> Option<usize> {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let last_chunk = (end - 1) & !0xF;
+        let mut chunk = start & !0xF;
+
+        loop {
+            let mask = within(self.mask(chunk as *const u8), chunk, start, end);
+            if mask != 0 {
+                return Some(chunk + mask.trailing_zeros() as usize - start);
+            }
+
+            if chunk == last_chunk {
+                return None;
+            }
+            chunk += BYTES_PER_OPERATION;
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn rfind(&self, haystack: &[u8]) -> Option<usize> {
+        if haystack.is_empty() {
+            return None;
+        }
+
+        let start = haystack.as_ptr() as usize;
+        let end = start + haystack.len();
+        let first_chunk = start & !0xF;
+        let mut chunk = (end - 1) & !0xF;
+
+        loop {
+            let mask = within(self.mask(chunk as *const u8), chunk, start, end);
+            if mask != 0 {
+                let last_match = 31 - mask.leading_zeros() as usize;
+                return Some(chunk + last_match - start);
+            }
+
+            if chunk == first_chunk {
+                return None;
+            }
+            chunk -= BYTES_PER_OPERATION;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Creates a set of bytes and an offset inside them.
+    fn haystack() -> BoxedStrategy<(Vec<u8>, usize)> {
+        any::<Vec<u8>>()
+            .prop_flat_map(|data| {
+                let len = 0..=data.len();
+                (Just(data), len)
+            })
+            .boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does(
+            (needle, (haystack, start)) in (any::<Vec<u8>>(), haystack())
+        ) {
+            let haystack = &haystack[start..];
+
+            let class = ByteClass::new(&ByteTable::new(&needle));
+
+            let us = unsafe { class.find(haystack) };
+            let them = haystack.iter().position(|b| needle.contains(b));
+            assert_eq!(us, them);
+
+            let us = unsafe { class.rfind(haystack) };
+            let them = haystack.iter().rposition(|b| needle.contains(b));
+            assert_eq!(us, them);
+        }
+    }
+
+    #[test]
+    fn every_byte_is_classified() {
+        let all: Vec<u8> = (0..=255).collect();
+
+        for b in 0..=255 {
+            let class = ByteClass::new(&ByteTable::new(&[b]));
+            unsafe {
+                assert_eq!(Some(usize::from(b)), class.find(&all));
+            }
+        }
+    }
+}
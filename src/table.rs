@@ -0,0 +1,219 @@
+/// A set of bytes stored as a 256-bit bitmap, with one bit for every
+/// possible byte. This is available on every platform and has no
+/// limit on the number of bytes in the set.
+#[derive(Clone)]
+pub struct ByteTable {
+    bits: [u64; 4],
+}
+
+impl ByteTable {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut bits = [0; 4];
+        for &b in bytes {
+            bits[usize::from(b >> 6)] |= 1 << (b & 0x3F);
+        }
+        ByteTable { bits }
+    }
+
+    /// Like `new`, for the first `len` bytes of an array.
+    pub const fn from_array(bytes: [u8; 16], len: usize) -> Self {
+        let mut bits = [0; 4];
+        let mut i = 0;
+        while i < len {
+            let b = bytes[i];
+            bits[(b >> 6) as usize] |= 1 << (b & 0x3F);
+            i += 1;
+        }
+        ByteTable { bits }
+    }
+
+    /// Like `from_array`, for pairs of the low and high bytes of
+    /// inclusive ranges.
+    // Only the fallback implementation builds tables this way.
+    #[cfg_attr(target_feature = "sse4.2", allow(dead_code))]
+    pub const fn from_ranges(ranges: [u8; 16], len: usize) -> Self {
+        let mut bits = [0; 4];
+        let mut i = 0;
+        while i + 1 < len {
+            let mut b = ranges[i];
+            while b <= ranges[i + 1] {
+                bits[(b >> 6) as usize] |= 1 << (b & 0x3F);
+                if b == u8::MAX {
+                    break;
+                }
+                b += 1;
+            }
+            i += 2;
+        }
+        ByteTable { bits }
+    }
+
+    pub fn insert(&mut self, b: u8) {
+        self.bits[usize::from(b >> 6)] |= 1 << (b & 0x3F);
+    }
+
+    /// Inserts every byte from `lo` to `hi`, inclusive.
+    pub fn insert_range(&mut self, lo: u8, hi: u8) {
+        for b in lo..=hi {
+            self.insert(b);
+        }
+    }
+
+    /// The bytes that are in either set.
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+
+    /// The bytes that are in both sets.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// The bytes that are in this set but not the other.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    fn zip(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        let mut bits = [0; 4];
+        for (w, (&a, &b)) in bits.iter_mut().zip(self.bits.iter().zip(&other.bits)) {
+            *w = f(a, b);
+        }
+        ByteTable { bits }
+    }
+
+    /// The set of every byte that is not in this one.
+    pub fn complement(&self) -> Self {
+        let mut bits = self.bits;
+        for w in &mut bits {
+            *w = !*w;
+        }
+        ByteTable { bits }
+    }
+
+    /// The bytes in the set as the fewest inclusive ranges, in
+    /// ascending order.
+    pub fn ranges(&self) -> Vec<(u8, u8)> {
+        let mut ranges: Vec<(u8, u8)> = Vec::new();
+        for b in self.bytes() {
+            match ranges.last_mut() {
+                Some(last) if last.1 + 1 == b => last.1 = b,
+                _ => ranges.push((b, b)),
+            }
+        }
+        ranges
+    }
+
+    #[inline]
+    pub fn contains(&self, b: u8) -> bool {
+        self.bits[usize::from(b >> 6)] & (1 << (b & 0x3F)) != 0
+    }
+
+    /// The number of distinct bytes in the set.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The bytes in the set, in ascending order.
+    pub fn bytes(&self) -> Vec<u8> {
+        (0..=255).filter(|&b| self.contains(b)).collect()
+    }
+}
+
+/// A set of up to 16 bytes stored as a list. The SIMD
+/// implementations that compare against one byte at a time all read
+/// the same list and broadcast each byte into their own vectors.
+#[cfg(any(target_arch = "x86_64", feature = "portable-simd"))]
+#[derive(Debug, Copy, Clone)]
+pub struct ByteList {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+#[cfg(any(target_arch = "x86_64", feature = "portable-simd"))]
+impl ByteList {
+    /// Uses the first `len` bytes of the array.
+    pub const fn new(bytes: [u8; 16], len: usize) -> Self {
+        ByteList { bytes, len }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn contains_exactly_the_provided_bytes(needle in any::<Vec<u8>>()) {
+            let table = ByteTable::new(&needle);
+            for b in 0..=255 {
+                assert_eq!(needle.contains(&b), table.contains(b));
+            }
+
+            let mut unique = needle.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            assert_eq!(unique.len(), table.count());
+            assert_eq!(unique, table.bytes());
+        }
+
+        #[test]
+        fn contains_exactly_the_bytes_in_the_ranges(
+            ranges in proptest::collection::vec(any::<(u8, u8)>(), 0..=8)
+        ) {
+            let mut array = [0; 16];
+            for (i, &(lo, hi)) in ranges.iter().enumerate() {
+                array[2 * i] = lo;
+                array[2 * i + 1] = hi;
+            }
+
+            let table = ByteTable::from_ranges(array, 2 * ranges.len());
+            for b in 0..=255 {
+                let expected = ranges.iter().any(|&(lo, hi)| lo <= b && b <= hi);
+                assert_eq!(expected, table.contains(b));
+            }
+        }
+
+        #[test]
+        fn ranges_and_complement_cover_the_same_bytes(needle in any::<Vec<u8>>()) {
+            let table = ByteTable::new(&needle);
+            let complement = table.complement();
+
+            let mut from_ranges = ByteTable::new(&[]);
+            for (lo, hi) in table.ranges() {
+                from_ranges.insert_range(lo, hi);
+            }
+
+            for b in 0..=255 {
+                assert_eq!(table.contains(b), from_ranges.contains(b));
+                assert_eq!(table.contains(b), !complement.contains(b));
+            }
+
+            let ranges = table.ranges();
+            for pair in ranges.windows(2) {
+                assert!(pair[0].1 + 1 < pair[1].0);
+            }
+        }
+
+        #[test]
+        fn set_operations_match_the_definitions(a in any::<Vec<u8>>(), b in any::<Vec<u8>>()) {
+            let (ta, tb) = (ByteTable::new(&a), ByteTable::new(&b));
+            let (union, intersection, difference) =
+                (ta.union(&tb), ta.intersection(&tb), ta.difference(&tb));
+
+            for x in 0..=255 {
+                let (in_a, in_b) = (a.contains(&x), b.contains(&x));
+                assert_eq!(union.contains(x), in_a || in_b);
+                assert_eq!(intersection.contains(x), in_a && in_b);
+                assert_eq!(difference.contains(x), in_a && !in_b);
+            }
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use frequency;
+
+/// The Two-Way string matching algorithm of Crochemore and Perrin,
+/// which finds a needle in `O(n + m)` time and constant space.
+///
+/// The needle is split at a critical position. Each candidate is
+/// checked by comparing the right half first, then the left half,
+/// and mismatches shift the candidate by an amount that never skips a
+/// match. For needles with a short period, the length of the prefix
+/// that is known to match after a shift is remembered so that it is
+/// not compared again; this is what keeps the search linear.
+///
+/// Every search accepts a *prefilter* that returns the position of
+/// the next (or previous) place in a slice where the needle's
+/// *anchor*, the rarest byte of the needle, might be, such as the
+/// anchor byte found using SIMD. The prefilter is only consulted when
+/// nothing is remembered about the current candidate, so it can only
+/// skip positions where a match cannot start. It may report false
+/// positives but must never miss a real match.
+///
+/// This follows the implementation of `str::find` in the standard
+/// library.
+pub struct TwoWay<'a> {
+    needle: &'a [u8],
+    crit_pos: usize,
+    crit_pos_back: usize,
+    period: usize,
+    long_period: bool,
+    anchor: usize,
+}
+
+impl<'a> TwoWay<'a> {
+    pub const fn new(needle: &'a [u8]) -> Self {
+        Self::with_anchor(needle, frequency::rarest_offset(needle))
+    }
+
+    /// Uses the needle byte at `anchor` instead of its rarest byte.
+    pub const fn with_anchor(needle: &'a [u8], anchor: usize) -> Self {
+        if needle.is_empty() {
+            return TwoWay {
+                needle,
+                crit_pos: 0,
+                crit_pos_back: 0,
+                period: 1,
+                long_period: false,
+                anchor: 0,
+            };
+        }
+
+        let (crit_pos_false, period_false) = maximal_suffix(needle, false);
+        let (crit_pos_true, period_true) = maximal_suffix(needle, true);
+
+        let (crit_pos, period) = if crit_pos_false > crit_pos_true {
+            (crit_pos_false, period_false)
+        } else {
+            (crit_pos_true, period_true)
+        };
+
+        if is_periodic(needle, crit_pos, period) {
+            // The needle is periodic, so remember how much of it
+            // matched after shifting by the period.
+            let crit_pos_back = needle.len()
+                - max(
+                    reverse_maximal_suffix(needle, period, false),
+                    reverse_maximal_suffix(needle, period, true),
+                );
+
+            TwoWay {
+                needle,
+                crit_pos,
+                crit_pos_back,
+                period,
+                long_period: false,
+                anchor,
+            }
+        } else {
+            // The period is long compared to the needle, so a shift
+            // by this lower bound is large enough without any memory.
+            TwoWay {
+                needle,
+                crit_pos,
+                crit_pos_back: crit_pos,
+                period: max(crit_pos, needle.len() - crit_pos) + 1,
+                long_period: true,
+                anchor,
+            }
+        }
+    }
+
+    #[cfg(feature = "pattern")]
+    pub fn needle(&self) -> &'a [u8] {
+        self.needle
+    }
+
+    /// The part of the needle that the prefilter should look for,
+    /// starting with the anchor byte.
+    #[cfg(any(test, all(not(target_arch = "x86_64"), not(feature = "portable-simd"))))]
+    pub fn anchor(&self) -> &'a [u8] {
+        &self.needle[self.anchor..]
+    }
+
+    /// The anchor byte itself, unless the needle is empty.
+    pub const fn anchor_byte(&self) -> Option<u8> {
+        if self.needle.is_empty() {
+            None
+        } else {
+            Some(self.needle[self.anchor])
+        }
+    }
+
+    /// Up to 16 bytes of the needle, starting with the anchor byte,
+    /// along with how many of them there are.
+    pub const fn anchor_prefix(&self) -> ([u8; 16], usize) {
+        let mut prefix = [0; 16];
+        let mut len = 0;
+        while len < prefix.len() && self.anchor + len < self.needle.len() {
+            prefix[len] = self.needle[self.anchor + len];
+            len += 1;
+        }
+        (prefix, len)
+    }
+
+    /// Searches for the first occurrence of the needle.
+    ///
+    /// The prefilter is given the rest of the haystack, starting
+    /// where the anchor of the current candidate would be, and
+    /// returns the offset of the first possible anchor within it.
+    /// Candidates too close to the end to hold the needle are
+    /// rejected afterwards, which lets the `PCMPESTRI` filter see the
+    /// bytes that follow them.
+    #[inline]
+    pub fn find<P>(&self, haystack: &[u8], mut prefilter: P) -> Option<usize>
+    where
+        P: FnMut(&[u8]) -> Option<usize>,
+    {
+        let needle = self.needle;
+        if needle.is_empty() {
+            return if haystack.is_empty() { None } else { Some(0) };
+        }
+        if needle.len() > haystack.len() {
+            return None;
+        }
+
+        let last_start = haystack.len() - needle.len();
+        let mut position = 0;
+        let mut memory = 0;
+
+        'search: loop {
+            if position > last_start {
+                return None;
+            }
+
+            if memory == 0 {
+                position += prefilter(&haystack[position + self.anchor..])?;
+                if position > last_start {
+                    return None;
+                }
+            }
+
+            // See if the right part of the needle matches
+            let start = if self.long_period {
+                self.crit_pos
+            } else {
+                max(self.crit_pos, memory)
+            };
+            for i in start..needle.len() {
+                if needle[i] != haystack[position + i] {
+                    position += i - self.crit_pos + 1;
+                    memory = 0;
+                    continue 'search;
+                }
+            }
+
+            // See if the left part of the needle matches
+            let start = if self.long_period { 0 } else { memory };
+            for i in (start..self.crit_pos).rev() {
+                if needle[i] != haystack[position + i] {
+                    position += self.period;
+                    if !self.long_period {
+                        memory = needle.len() - self.period;
+                    }
+                    continue 'search;
+                }
+            }
+
+            return Some(position);
+        }
+    }
+
+    /// Searches for the last occurrence of the needle.
+    ///
+    /// The prefilter is given the positions where the anchor of a
+    /// candidate could be and returns the offset of the last possible
+    /// anchor within them.
+    #[inline]
+    pub fn rfind<P>(&self, haystack: &[u8], mut prefilter: P) -> Option<usize>
+    where
+        P: FnMut(&[u8]) -> Option<usize>,
+    {
+        let needle = self.needle;
+        if needle.is_empty() {
+            return haystack.len().checked_sub(1);
+        }
+        if needle.len() > haystack.len() {
+            return None;
+        }
+
+        // The candidate is `haystack[end - needle.len()..end]`
+        let mut end = haystack.len();
+        let mut memory = needle.len();
+
+        'search: loop {
+            if end < needle.len() {
+                return None;
+            }
+
+            if memory == needle.len() {
+                let anchors = self.anchor..=end - needle.len() + self.anchor;
+                let start = prefilter(&haystack[anchors])?;
+                end = start + needle.len();
+            }
+
+            // See if the left part of the needle matches
+            let crit = if self.long_period {
+                self.crit_pos_back
+            } else {
+                min(self.crit_pos_back, memory)
+            };
+            for i in (0..crit).rev() {
+                if needle[i] != haystack[end - needle.len() + i] {
+                    end -= self.crit_pos_back - i;
+                    memory = needle.len();
+                    continue 'search;
+                }
+            }
+
+            // See if the right part of the needle matches
+            let needle_end = if self.long_period { needle.len() } else { memory };
+            for i in self.crit_pos_back..needle_end {
+                if needle[i] != haystack[end - needle.len() + i] {
+                    end -= self.period;
+                    if !self.long_period {
+                        memory = self.period;
+                    }
+                    continue 'search;
+                }
+            }
+
+            return Some(end - needle.len());
+        }
+    }
+}
+
+// `cmp::max` and `cmp::min` can't be used in a `const fn`.
+const fn max(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+const fn min(a: usize, b: usize) -> usize {
+    if a < b { a } else { b }
+}
+
+/// Checks if the part of the needle before the critical position
+/// repeats after `period` bytes.
+const fn is_periodic(needle: &[u8], crit_pos: usize, period: usize) -> bool {
+    if period + crit_pos > needle.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < crit_pos {
+        if needle[i] != needle[period + i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Computes the maximal suffix of `arr` under the normal ordering
+/// (or the reverse ordering, if `order_greater` is set), returning
+/// its starting position and period.
+const fn maximal_suffix(arr: &[u8], order_greater: bool) -> (usize, usize) {
+    let mut left = 0;
+    let mut right = 1;
+    let mut offset = 0;
+    let mut period = 1;
+
+    while right + offset < arr.len() {
+        let a = arr[right + offset];
+        // `left` will be in bounds when `right` is.
+        let b = arr[left + offset];
+        if (a < b && !order_greater) || (a > b && order_greater) {
+            // Suffix is smaller, period is entire prefix so far.
+            right += offset + 1;
+            offset = 0;
+            period = right - left;
+        } else if a == b {
+            // Advance through repetition of the current period.
+            if offset + 1 == period {
+                right += offset + 1;
+                offset = 0;
+            } else {
+                offset += 1;
+            }
+        } else {
+            // Suffix is larger, start over from current location.
+            left = right;
+            right += 1;
+            offset = 0;
+            period = 1;
+        }
+    }
+
+    (left, period)
+}
+
+/// Like `maximal_suffix`, but for the reversed needle, stopping once
+/// the period reaches `known_period`. Returns the length of the
+/// reversed suffix.
+const fn reverse_maximal_suffix(arr: &[u8], known_period: usize, order_greater: bool) -> usize {
+    let mut left = 0;
+    let mut right = 1;
+    let mut offset = 0;
+    let mut period = 1;
+    let n = arr.len();
+
+    while right + offset < n {
+        let a = arr[n - (1 + right + offset)];
+        let b = arr[n - (1 + left + offset)];
+        if (a < b && !order_greater) || (a > b && order_greater) {
+            right += offset + 1;
+            offset = 0;
+            period = right - left;
+        } else if a == b {
+            if offset + 1 == period {
+                right += offset + 1;
+                offset = 0;
+            } else {
+                offset += 1;
+            }
+        } else {
+            left = right;
+            right += 1;
+            offset = 0;
+            period = 1;
+        }
+
+        if period == known_period {
+            break;
+        }
+    }
+
+    debug_assert!(period <= known_period);
+    left
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::{collection::vec, prelude::*};
+
+    use super::*;
+
+    /// Reports every position as a candidate.
+    fn first(candidates: &[u8]) -> Option<usize> {
+        if candidates.is_empty() { None } else { Some(0) }
+    }
+
+    /// Reports every position as a candidate.
+    fn last(candidates: &[u8]) -> Option<usize> {
+        candidates.len().checked_sub(1)
+    }
+
+    /// A small alphabet makes periodic needles and partial matches
+    /// common.
+    fn text(max_len: usize) -> BoxedStrategy<Vec<u8>> {
+        vec(prop_oneof![Just(b'a'), Just(b'b'), Just(b'c')], 0..max_len).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn works_as_find_does(needle in text(16), haystack in text(256)) {
+            let two_way = TwoWay::new(&needle);
+
+            let us = two_way.find(&haystack, first);
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = two_way.rfind(&haystack, last);
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+
+        #[test]
+        fn works_with_an_anchor_prefilter(needle in text(16), haystack in text(256)) {
+            let two_way = TwoWay::new(&needle);
+            let anchor = two_way.anchor().first().cloned();
+
+            let us = two_way.find(&haystack, |c| c.iter().position(|&b| Some(b) == anchor));
+            let them = (0..haystack.len()).find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+
+            let us = two_way.rfind(&haystack, |c| c.iter().rposition(|&b| Some(b) == anchor));
+            let them = (0..haystack.len()).rev().find(|&i| haystack[i..].starts_with(&needle));
+            assert_eq!(us, them);
+        }
+    }
+
+    #[test]
+    fn can_be_built_at_compile_time() {
+        const TWO_WAY: TwoWay<'static> = TwoWay::new(b"abcabd");
+
+        assert_eq!(Some(b'b'), TWO_WAY.anchor_byte());
+        assert_eq!(Some(3), TWO_WAY.find(b"abcabcabd", first));
+        assert_eq!(Some(3), TWO_WAY.rfind(b"abcabcabd", last));
+    }
+
+    #[test]
+    fn pathological_needles_take_linear_time() {
+        // A naive search would perform about a billion comparisons.
+        let haystack = vec![b'a'; 1_000_000];
+        let mut needle = vec![b'a'; 1_000];
+
+        needle.push(b'b');
+        let two_way = TwoWay::new(&needle);
+        assert_eq!(None, two_way.find(&haystack, first));
+        assert_eq!(None, two_way.rfind(&haystack, last));
+
+        needle.insert(0, b'b');
+        needle.pop();
+        let two_way = TwoWay::new(&needle);
+        assert_eq!(None, two_way.find(&haystack, first));
+        assert_eq!(None, two_way.rfind(&haystack, last));
+    }
+}